@@ -1,479 +1,5592 @@
-use solana_sdk::signature::{Keypair, Signer};
+// solana_client's ClientError is large, and the retried RPC closures return it as is.
+#![allow(clippy::result_large_err)]
+
+use solana_sdk::signature::{Keypair, Signature, Signer};
 use solana_sdk::bs58;
 
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, NewAead};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::{rngs::OsRng, RngCore};
 use dotenvy::dotenv;
+use serde_json::json;
 use std::env;
-use serde_json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::io::{IsTerminal, Write};
 
-use solana_client::rpc_client::RpcClient;
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::{RpcSignatureSubscribeConfig, RpcTransactionConfig};
+use solana_client::rpc_response::{ProcessedSignatureResult, RpcSignatureResult, RpcSimulateTransactionResult};
+use solana_client::pubsub_client::{PubsubClient, SignatureSubscription};
+use solana_transaction_status::{TransactionStatus, UiTransactionEncoding};
+use solana_client::rpc_request::{TokenAccountsFilter, MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS, MAX_MULTIPLE_ACCOUNTS};
+use solana_account_decoder::UiAccountData;
 use solana_sdk::{
+    account::Account,
     commitment_config::CommitmentConfig,
+    compute_budget::ComputeBudgetInstruction,
+    hash::Hash,
+    instruction::Instruction,
+    nonce,
+    message::Message,
     native_token::LAMPORTS_PER_SOL,
-    pubkey::Pubkey,
+    packet::PACKET_DATA_SIZE,
+    pubkey::{Pubkey, MAX_SEEDS, MAX_SEED_LEN},
+    transaction::TransactionError,
 };
-use tokio;
 use std::str::FromStr;
 
 use std::time::{Instant, Duration};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use std::thread;
 
-use clap::{Arg, Command, ArgAction};
+use clap::{Arg, ArgGroup, Command, ArgAction};
+use clap::parser::ValueSource;
 
 use solana_sdk:: {
     system_instruction,
     transaction::Transaction,
 };
 
-use spl_token::{
+use spl_token_2022::{
+    extension::StateWithExtensions,
     instruction::initialize_mint,
-    state::Mint,
+    state::{Account as TokenAccount, Mint, Multisig},
 };
+use solana_sdk::program_option::COption;
 use solana_sdk::program_pack::Pack;
 
-use spl_associated_token_account::instruction::create_associated_token_account;
-use spl_associated_token_account::get_associated_token_address;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
 
-use spl_token::instruction::mint_to;
+use spl_token_2022::instruction::{
+    burn_checked, close_account, freeze_account, mint_to, set_authority, sync_native, thaw_account,
+    transfer_checked, AuthorityType,
+};
 
 use mpl_token_metadata::types::DataV2;
-use mpl_token_metadata::instructions::{CreateMetadataAccountV3, CreateMetadataAccountV3InstructionArgs};
+use mpl_token_metadata::accounts::Metadata;
+use mpl_token_metadata::{MAX_NAME_LENGTH, MAX_SYMBOL_LENGTH, MAX_URI_LENGTH};
+use mpl_token_metadata::instructions::{
+    CreateMetadataAccountV3, CreateMetadataAccountV3InstructionArgs,
+    UpdateMetadataAccountV2, UpdateMetadataAccountV2InstructionArgs, BurnV1Builder,
+};
 use solana_sdk::system_program;
+use solana_sdk::derivation_path::DerivationPath;
+use solana_remote_wallet::locator::Locator;
+use solana_remote_wallet::remote_keypair::generate_remote_keypair;
+use solana_remote_wallet::remote_wallet::maybe_wallet_manager;
+use solana_sdk::signer::keypair::keypair_from_seed_and_derivation_path;
+use bip39::{Language, Mnemonic, Seed};
 
-#[tokio::main]
-async fn main() {
-    let matches = Command::new("Solana CLI")
+const DEVNET_RPC_URL: &str = "https://api.devnet.solana.com";
+const STATE_FILE: &str = ".solana-cli-state.json";
+const MAX_MEMO_BYTES: usize = 566;
+const MINT_PREFIX_TIMEOUT_MINUTES: u64 = 3;
+const VANITY_BENCHMARK_SECS: u64 = 1;
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const AUTO_RPC_TIMEOUT_SECS: u64 = 5;
+const KEYSTORE_SALT_BYTES: usize = 16;
+const KEYSTORE_NONCE_BYTES: usize = 12;
+const MAX_CONCURRENT_RPC_REQUESTS: usize = 4;
+const DEFAULT_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
+const WEBSOCKET_CONFIRM_TIMEOUT_SECS: u64 = 60;
+const TOOL_CONFIG_PATH: &str = ".config/solana-cli-tool/config.json";
+const TOOL_CONFIG_ENV: &str = "SOLANA_CLI_TOOL_CONFIG";
+// A just confirmed transaction can take a moment to show up in getTransaction.
+const REPORT_FETCH_ATTEMPTS: u32 = 5;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_BAD_ARGS: i32 = 2;
+const EXIT_RPC_FAILURE: i32 = 3;
+const EXIT_INSUFFICIENT_FUNDS: i32 = 4;
+// What a shell reports for a process killed by SIGINT.
+const EXIT_INTERRUPTED: i32 = 130;
+
+// Set by Ctrl-C while an Interruptible section runs, which then stops at its next check.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static INTERRUPTIBLE: AtomicBool = AtomicBool::new(false);
+
+struct Config {
+    keypair_path: Option<String>,
+    keystore: Option<String>,
+    wallet: Option<String>,
+    wallet_secret: Option<String>,
+    ledger: Option<DerivationPath>,
+    rpc_url: String,
+    token_program_id: Pubkey,
+    json: bool,
+    dry_run: bool,
+    simulate: bool,
+    blockhash: Option<Hash>,
+    offline: Option<String>,
+    max_retries: u32,
+    commitment: CommitmentConfig,
+    yes: bool,
+    priority_fee: Option<u64>,
+    compute_limit: Option<u32>,
+    nonce_account: Option<Pubkey>,
+    nonce_authority: Option<Keypair>,
+    output_dir: Option<String>,
+    websocket: bool,
+    fee_payer: Option<Pubkey>,
+    fee_payer_keypair: Option<Keypair>,
+    max_fee: u64,
+    explorer_links: bool,
+    metadata_program_id: Pubkey,
+    quiet: bool,
+    dump_tx: bool,
+}
+
+// The JSON fields --quiet reduces a result to, in order of preference.
+const QUIET_FIELDS: [&str; 4] = ["signature", "sol", "mint", "pubkey"];
+
+impl Config {
+    // Progress messages go to stderr in --json mode so stdout stays parseable.
+    fn info(&self, message: impl std::fmt::Display) {
+        if self.quiet {
+            return;
+        }
+        if self.json {
+            eprintln!("{}", message);
+        } else {
+            println!("{}", message);
+        }
+    }
+
+    fn output(&self, human: impl std::fmt::Display, json: serde_json::Value) {
+        if self.json {
+            println!("{}", json);
+        } else if let Some(essential) = self.quiet.then(|| quiet_output(&json)).flatten() {
+            println!("{}", essential);
+        } else {
+            println!("{}", human);
+        }
+    }
+
+    // An explorer URL, or the bare identifier with --no-explorer-links.
+    fn link(&self, kind: &str, id: impl std::fmt::Display) -> String {
+        if self.explorer_links {
+            explorer_link(kind, id, &self.rpc_url)
+        } else {
+            id.to_string()
+        }
+    }
+
+    // False when --dry-run, --simulate or --offline stop transactions from being broadcast.
+    fn sends_transactions(&self) -> bool {
+        !self.dry_run && !self.simulate && self.offline.is_none()
+    }
+
+    // Places a relative file path in --output-dir, creating the directory if needed.
+    fn output_file(&self, path: &str) -> Result<String, CliError> {
+        let Some(output_dir) = &self.output_dir else {
+            return Ok(path.to_string());
+        };
+        fs::create_dir_all(output_dir)
+            .map_err(|e| format!("Failed to create the output directory {}: {}", output_dir, e))?;
+        Ok(Path::new(output_dir).join(path).to_string_lossy().into_owned())
+    }
+
+    // Names a file <kind>-<pubkey>.json in --output-dir, if one was given.
+    fn default_output_file(&self, kind: &str, pubkey: &Pubkey) -> Result<Option<String>, CliError> {
+        if self.output_dir.is_none() {
+            return Ok(None);
+        }
+        self.output_file(&format!("{}-{}.json", kind, pubkey)).map(Some)
+    }
+}
+
+fn mint_arg() -> Arg {
+    Arg::new("mint")
+        .long("mint")
+        .value_name("PUBKEY")
+        .help("Token mint to work with [default: the mint saved by create-mint]")
+}
+
+fn amount_arg(help: &'static str) -> Arg {
+    Arg::new("amount")
+        .long("amount")
+        .value_name("AMOUNT")
+        .value_parser(clap::value_parser!(f64))
+        .required(true)
+        .help(help)
+}
+
+// Like amount_arg, but parsed into lamports by parse_sol_amount.
+fn sol_amount_arg(help: &'static str) -> Arg {
+    Arg::new("amount")
+        .long("amount")
+        .value_name("AMOUNT")
+        .value_parser(parse_sol_amount)
+        .required(true)
+        .help(help)
+}
+
+// Accepts `0.01sol`, `100000lamports` or a bare number of SOL, returning lamports.
+// Decimal SOL is parsed exactly so amounts never pick up float rounding.
+fn parse_sol_amount(value: &str) -> Result<u64, String> {
+    let value = value.trim().to_lowercase();
+    if let Some(lamports) = value.strip_suffix("lamports").or_else(|| value.strip_suffix("lamport")) {
+        return lamports.trim().parse::<u64>()
+            .map_err(|_| format!("{:?} is not a whole number of lamports", lamports.trim()));
+    }
+
+    let sol = value.strip_suffix("sol").unwrap_or(&value).trim();
+    let (whole, fraction) = sol.split_once('.').unwrap_or((sol, ""));
+    let decimals = LAMPORTS_PER_SOL.ilog10() as usize;
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole.bytes().all(|byte| byte.is_ascii_digit())
+        || !fraction.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        return Err(format!("{:?} is not an amount of SOL, use e.g. 0.01sol or 100000lamports", sol));
+    }
+    if fraction.len() > decimals {
+        return Err(format!("{} has more than {} decimal places, the smallest unit is 1 lamport", sol, decimals));
+    }
+
+    let whole = if whole.is_empty() { 0 } else { whole.parse::<u64>().map_err(|e| e.to_string())? };
+    let fraction = if fraction.is_empty() { 0 } else { format!("{:0<width$}", fraction, width = decimals).parse::<u64>().map_err(|e| e.to_string())? };
+    whole.checked_mul(LAMPORTS_PER_SOL)
+        .and_then(|lamports| lamports.checked_add(fraction))
+        .ok_or_else(|| format!("{} SOL is too large", sol))
+}
+
+// A zero limit would block every transaction, so it is refused up front.
+fn parse_max_fee(value: &str) -> Result<u64, String> {
+    match parse_sol_amount(value)? {
+        0 => Err("the fee limit must be greater than zero".to_string()),
+        lamports => Ok(lamports),
+    }
+}
+
+// Named account sizes rent-exemption --size accepts besides a number of bytes.
+const ACCOUNT_SIZE_PRESETS: [(&str, usize); 3] = [
+    ("mint", Mint::LEN),
+    ("token-account", TokenAccount::LEN),
+    ("multisig", Multisig::LEN),
+];
+
+fn parse_account_size(value: &str) -> Result<usize, String> {
+    if let Some((_, size)) = ACCOUNT_SIZE_PRESETS.iter().find(|(name, _)| *name == value) {
+        return Ok(*size);
+    }
+    value.parse::<usize>().map_err(|_| format!(
+        "expected a number of bytes or one of {}",
+        ACCOUNT_SIZE_PRESETS.map(|(name, _)| name).join(", ")
+    ))
+}
+
+fn build_cli() -> Command {
+    Command::new("Solana CLI")
         .version("0.2.0")
         .author("vlevko")
         .about("A multi-function Solana tool")
-        .arg(Arg::new("generate-keypair")
-            .short('g')
-            .long("generate-keypair")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(Arg::new("keypair")
+            .short('k')
+            .long("keypair")
+            .value_name("PATH")
+            .global(true)
+            .help("Sign with the keypair in this Solana CLI style JSON file instead of .env SECRET_KEY"))
+        .arg(Arg::new("keystore")
+            .long("keystore")
+            .value_name("PATH")
+            .global(true)
+            .conflicts_with("keypair")
+            .help("Sign with the keypair in this passphrase encrypted keystore (see create-keystore), the passphrase is read from SOLANA_PASSPHRASE or prompted"))
+        .arg(Arg::new("ledger")
+            .long("ledger")
+            .value_name("DERIVATION_PATH")
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("m/44'/501'")
+            .global(true)
+            .conflicts_with_all(["keypair", "keystore", "wallet"])
+            .help("Sign with the connected Ledger hardware wallet, optionally at --ledger=DERIVATION_PATH [default: m/44'/501']"))
+        .arg(Arg::new("wallet")
+            .long("wallet")
+            .value_name("NAME")
+            .global(true)
+            .conflicts_with_all(["keypair", "keystore"])
+            .help("Sign with this named wallet from ~/.config/solana-cli-tool/config.json or $SOLANA_CLI_TOOL_CONFIG"))
+        .arg(Arg::new("endpoint")
+            .long("endpoint")
+            .value_name("NAME")
+            .global(true)
+            .conflicts_with_all(["rpc-url", "cluster"])
+            .help("Talk to this named RPC endpoint from the config file"))
+        .arg(Arg::new("rpc-url")
+            .short('u')
+            .long("rpc-url")
+            .value_name("URL")
+            .global(true)
+            .help("JSON RPC endpoint to talk to [default: devnet]"))
+        .arg(Arg::new("cluster")
+            .long("cluster")
+            .value_name("CLUSTER")
+            .value_parser(["devnet", "testnet", "mainnet-beta", "localhost"])
+            .global(true)
+            .help("Use the canonical RPC endpoint of this cluster"))
+        .arg(Arg::new("auto-rpc")
+            .long("auto-rpc")
             .action(ArgAction::SetTrue)
-            .help("Generate a new keypair"))
-        .arg(Arg::new("load-keypair")
-            .short('l')
-            .long("load-keypair")
+            .global(true)
+            .conflicts_with_all(["rpc-url", "endpoint"])
+            .help("Time getSlot on the endpoint of --cluster and its mirrors from the config file, and use the fastest healthy one"))
+        .arg(Arg::new("token-2022")
+            .long("token-2022")
             .action(ArgAction::SetTrue)
-            .help("Load keypair from .env SECRET_KEY"))
-        .arg(Arg::new("check-balance")
-            .short('c')
-            .long("check-balance")
+            .global(true)
+            .help("Use the Token-2022 program instead of the legacy SPL token program"))
+        .arg(Arg::new("quiet")
+            .short('q')
+            .long("quiet")
             .action(ArgAction::SetTrue)
-            .help("Check balance on devnet and request airdrop if required"))
-        .arg(Arg::new("find-keypair")
-            .short('f')
-            .long("find-keypair")
+            .global(true)
+            .help("Hide progress messages and print only the signature, balance or address a command results in"))
+        .arg(Arg::new("json")
+            .long("json")
             .action(ArgAction::SetTrue)
-            .help("Find a new keypair with the public key starting with 'Lev' within 3 minutes"))
-        .arg(Arg::new("send-sol")
-            .short('s')
-            .long("send-sol")
+            .global(true)
+            .help("Print results as JSON for scripting"))
+        .arg(Arg::new("dry-run")
+            .long("dry-run")
             .action(ArgAction::SetTrue)
-            .help("Send 0.01 SOL to the hardcoded wallet address"))
-        .arg(Arg::new("create-token-mint")
-            .short('m')
-            .long("create-token-mint")
+            .global(true)
+            .help("Estimate the fee of each transaction without sending it"))
+        .arg(Arg::new("simulate")
+            .long("simulate")
             .action(ArgAction::SetTrue)
-            .help("Create a new token mint"))
-        .arg(Arg::new("create-token-account")
-            .short('a')
-            .long("create-token-account")
+            .global(true)
+            .conflicts_with("dry-run")
+            .help("Simulate each transaction and print its program logs instead of sending it"))
+        .arg(Arg::new("dump-tx")
+            .long("dump-tx")
             .action(ArgAction::SetTrue)
-            .help("Create a new token account"))
-        .arg(Arg::new("mint-tokens")
-            .short('t')
-            .long("mint-tokens")
+            .global(true)
+            .help("Print each built transaction, base64 encoded and decoded into its instructions, before sending it"))
+        .arg(Arg::new("max-retries")
+            .long("max-retries")
+            .value_name("N")
+            .value_parser(clap::value_parser!(u32))
+            .default_value("3")
+            .global(true)
+            .help("Retry RPC calls failing with transient errors up to N times"))
+        .arg(Arg::new("blockhash")
+            .long("blockhash")
+            .value_name("HASH")
+            .global(true)
+            .help("Sign with this recent blockhash instead of fetching one"))
+        .arg(Arg::new("offline")
+            .long("offline")
+            .value_name("FILE")
+            .requires("blockhash")
+            .global(true)
+            .help("Write the signed transaction to FILE as base64 instead of sending it, see the submit command"))
+        .arg(Arg::new("fee-payer")
+            .long("fee-payer")
+            .value_name("KEYPAIR|PUBKEY")
+            .global(true)
+            .help("Let this account pay the fees; given a PUBKEY the --offline transaction is left for it to sign with add-signature"))
+        .arg(Arg::new("output-dir")
+            .long("output-dir")
+            .value_name("DIR")
+            .global(true)
+            .help("Write files into DIR, naming keypairs and keystores <kind>-<pubkey>.json unless --out is given"))
+        .arg(Arg::new("yes")
+            .short('y')
+            .long("yes")
             .action(ArgAction::SetTrue)
-            .help("Mint some tokens"))
-        .arg(Arg::new("create-token-metadata")
-            .short('d')
-            .long("create-token-metadata")
+            .global(true)
+            .help("Skip the confirmation prompt before moving value on mainnet-beta"))
+        .arg(Arg::new("priority-fee")
+            .long("priority-fee")
+            .value_name("MICROLAMPORTS")
+            .value_parser(clap::value_parser!(u64))
+            .global(true)
+            .help("Compute unit price to pay as a priority fee, in micro-lamports [default: none]"))
+        .arg(Arg::new("max-fee")
+            .long("max-fee")
+            .value_name("AMOUNT")
+            .value_parser(parse_max_fee)
+            .default_value("0.01")
+            .global(true)
+            .help("Abort when the estimated fee of a transaction exceeds this amount, e.g. 0.01, 0.01sol or 10000000lamports"))
+        .arg(Arg::new("compute-limit")
+            .long("compute-limit")
+            .value_name("UNITS")
+            .value_parser(clap::value_parser!(u32))
+            .global(true)
+            .help("Compute unit limit requested for each transaction"))
+        .arg(Arg::new("nonce-account")
+            .long("nonce-account")
+            .value_name("PUBKEY")
+            .global(true)
+            .help("Use the blockhash stored in this durable nonce account and advance it"))
+        .arg(Arg::new("nonce-authority")
+            .long("nonce-authority")
+            .value_name("KEYPAIR")
+            .requires("nonce-account")
+            .global(true)
+            .help("Keypair file of the nonce authority [default: the fee payer]"))
+        .arg(Arg::new("websocket")
+            .long("websocket")
             .action(ArgAction::SetTrue)
-            .help("Create some token metadata"))
-        .get_matches();
-        
-    if matches.get_flag("generate-keypair") {
-        generate_keypair();
-    } else if matches.get_flag("load-keypair") {
-        load_keypair();
-    } else if matches.get_flag("check-balance") {
-        check_balance().await;
-    } else if matches.get_flag("find-keypair") {
-        find_keypair("Lev", 3);
-    } else if matches.get_flag("send-sol") {
-        if let Err(e) = send_sol() {
-            println!("Sending SOL failed due to: {:?}", e);
+            .global(true)
+            .help("Wait for confirmations over a WebSocket signatureSubscribe instead of polling the RPC"))
+        .arg(Arg::new("metadata-program-id")
+            .long("metadata-program-id")
+            .value_name("PUBKEY")
+            .global(true)
+            .help("Token metadata program to use, e.g. a forked or localnet deployment [default: Metaplex Token Metadata]"))
+        .arg(Arg::new("no-explorer-links")
+            .long("no-explorer-links")
+            .action(ArgAction::SetTrue)
+            .global(true)
+            .help("Print bare addresses and signatures instead of Solana Explorer URLs"))
+        .arg(Arg::new("commitment")
+            .long("commitment")
+            .value_name("LEVEL")
+            .value_parser(["processed", "confirmed", "finalized"])
+            .default_value("confirmed")
+            .global(true)
+            .help("Commitment level used for reading state and confirming transactions"))
+        .arg(Arg::new("verbose")
+            .short('v')
+            .long("verbose")
+            .action(ArgAction::Count)
+            .global(true)
+            .help("Log more details to stderr, repeat for more (-v info, -vv debug, -vvv trace)"))
+        .subcommand(Command::new("fee-stats")
+            .about("Print percentiles of the priority fees paid in recent slots, to help choose --priority-fee")
+            .arg(Arg::new("account")
+                .long("account")
+                .value_name("PUBKEY")
+                .action(ArgAction::Append)
+                .help("Only count transactions that write to this account, repeat for each account a transaction touches")))
+        .subcommand(Command::new("rpc-info")
+            .about("Print the health, software version and current slot of the RPC endpoint"))
+        .subcommand(Command::new("show-config")
+            .about("Print the RPC endpoint, keypair and commitment the other commands would use, and where each comes from"))
+        .subcommand(Command::new("generate")
+            .about("Generate a new keypair")
+            .arg(Arg::new("out")
+                .long("out")
+                .value_name("PATH")
+                .help("Write the keypair to a Solana CLI style JSON file"))
+            .arg(Arg::new("force")
+                .long("force")
+                .action(ArgAction::SetTrue)
+                .help("Overwrite the --out file if it already exists"))
+            .arg(Arg::new("show-secret")
+                .long("show-secret")
+                .action(ArgAction::SetTrue)
+                .help("Print the secret key to stdout (it is hidden by default)"))
+            .arg(Arg::new("count")
+                .long("count")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .default_value("1")
+                .help("Number of keypairs to generate, written to <out>-0.json, <out>-1.json, ... with --out")))
+        .subcommand(Command::new("from-mnemonic")
+            .about("Derive a keypair from a BIP39 seed phrase the way the Solana CLI does")
+            .arg(Arg::new("mnemonic")
+                .long("mnemonic")
+                .value_name("PHRASE")
+                .help("BIP39 seed phrase [default: SOLANA_MNEMONIC from the environment or .env]"))
+            .arg(Arg::new("passphrase")
+                .long("passphrase")
+                .value_name("STR")
+                .default_value("")
+                .help("Optional BIP39 passphrase protecting the seed phrase"))
+            .arg(Arg::new("derivation-path")
+                .long("derivation-path")
+                .value_name("PATH")
+                .default_value(DEFAULT_DERIVATION_PATH)
+                .help("BIP44 derivation path, every level is hardened"))
+            .arg(Arg::new("out")
+                .long("out")
+                .value_name("PATH")
+                .help("Write the keypair to a Solana CLI style JSON file"))
+            .arg(Arg::new("force")
+                .long("force")
+                .action(ArgAction::SetTrue)
+                .help("Overwrite the --out file if it already exists"))
+            .arg(Arg::new("show-secret")
+                .long("show-secret")
+                .action(ArgAction::SetTrue)
+                .help("Print the secret key to stdout (it is hidden by default)")))
+        .subcommand(Command::new("load")
+            .about("Load keypair from --keypair, --keystore or .env SECRET_KEY"))
+        .subcommand(Command::new("create-keystore")
+            .about("Encrypt the keypair from --keypair or .env SECRET_KEY into a passphrase protected keystore")
+            .arg(Arg::new("out")
+                .long("out")
+                .value_name("PATH")
+                .help("Keystore file to write [default: keystore-<pubkey>.json in --output-dir]"))
+            .arg(Arg::new("force")
+                .long("force")
+                .action(ArgAction::SetTrue)
+                .help("Overwrite the --out file if it already exists")))
+        .subcommand(Command::new("sign-message")
+            .about("Sign --message with the loaded keypair and print the base58 signature")
+            .arg(Arg::new("message")
+                .long("message")
+                .value_name("STR")
+                .required(true)
+                .help("Message to sign")))
+        .subcommand(Command::new("verify-message")
+            .about("Check that --signature of --message was made by --pubkey")
+            .arg(Arg::new("pubkey")
+                .long("pubkey")
+                .value_name("PUBKEY")
+                .required(true)
+                .help("Public key of the signer"))
+            .arg(Arg::new("message")
+                .long("message")
+                .value_name("STR")
+                .required(true)
+                .help("Message that was signed"))
+            .arg(Arg::new("signature")
+                .long("signature")
+                .value_name("SIG")
+                .required(true)
+                .help("Base58 signature of the message")))
+        .subcommand(Command::new("balance")
+            .about("Check the balance of --address, or of the loaded keypair and request an airdrop if required")
+            .arg(Arg::new("address")
+                .long("address")
+                .value_name("PUBKEY")
+                .help("Wallet address to inspect [default: the loaded keypair's public key]"))
+            .arg(Arg::new("airdrop-amount")
+                .long("airdrop-amount")
+                .value_name("SOL")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("0.5")
+                .help("Amount of SOL to request when the balance is below --min-balance"))
+            .arg(Arg::new("min-balance")
+                .long("min-balance")
+                .value_name("SOL")
+                .value_parser(clap::value_parser!(f64))
+                .default_value("1.5")
+                .help("Request an airdrop when the balance drops below this amount"))
+            .arg(Arg::new("no-airdrop")
+                .long("no-airdrop")
+                .action(ArgAction::SetTrue)
+                .help("Only read the balance, never request an airdrop"))
+            .arg(Arg::new("airdrop-timeout")
+                .long("airdrop-timeout")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("60")
+                .help("Give up waiting for the airdrop to confirm after this many seconds"))
+            .arg(Arg::new("tokens")
+                .long("tokens")
+                .action(ArgAction::SetTrue)
+                .help("Also list the SPL token balances of the wallet")))
+        .subcommand(Command::new("check-balances")
+            .about("Print the SOL balances of all addresses listed in --file")
+            .arg(Arg::new("file")
+                .long("file")
+                .value_name("PATH")
+                .required(true)
+                .help("File with one address per line, blank lines and lines starting with # are skipped")))
+        .subcommand(Command::new("airdrop")
+            .about("Request an airdrop of --amount regardless of the current balance")
+            .arg(sol_amount_arg("Amount to request, e.g. 0.5, 0.5sol or 500000000lamports"))
+            .arg(Arg::new("address")
+                .long("address")
+                .value_name("PUBKEY")
+                .help("Wallet address to fund [default: the loaded keypair's public key]"))
+            .arg(Arg::new("timeout")
+                .long("timeout")
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("60")
+                .help("Give up waiting for the airdrop to confirm after this many seconds")))
+        .subcommand(Command::new("check-rent")
+            .about("Check whether an account holds enough lamports to be rent exempt")
+            .arg(Arg::new("address")
+                .long("address")
+                .value_name("PUBKEY")
+                .required(true)
+                .help("Account to inspect")))
+        .subcommand(Command::new("rent-exemption")
+            .about("Print the minimum balance an account of --size bytes needs to be rent exempt")
+            .arg(Arg::new("size")
+                .long("size")
+                .value_name("BYTES")
+                .value_parser(parse_account_size)
+                .required(true)
+                .help("Account data size in bytes, or one of mint, token-account, multisig")))
+        .subcommand(Command::new("history")
+            .about("List the recent transactions of a wallet")
+            .arg(Arg::new("address")
+                .long("address")
+                .value_name("PUBKEY")
+                .help("Wallet address to inspect [default: the loaded keypair's public key]"))
+            .arg(Arg::new("limit")
+                .long("limit")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("10")
+                .help("Number of most recent transactions to list"))
+            .arg(Arg::new("details")
+                .long("details")
+                .action(ArgAction::SetTrue)
+                .help("Also fetch each transaction to show its fee and the balance change of the wallet")))
+        .subcommand(Command::new("vanity")
+            .about("Find a new keypair with the public key matching --prefix and/or --suffix within --timeout-minutes")
+            .arg(Arg::new("prefix")
+                .long("prefix")
+                .value_name("STR")
+                .help("Base58 prefix the public key should start with [default: Lev, unless another pattern is given]"))
+            .arg(Arg::new("prefix-any")
+                .long("prefix-any")
+                .value_name("STR,STR,...")
+                .value_delimiter(',')
+                .conflicts_with("prefix")
+                .help("Accept a public key starting with any of these base58 prefixes"))
+            .arg(Arg::new("prefix-class")
+                .long("prefix-class")
+                .value_name("CLASS")
+                .value_parser(VANITY_CLASSES.map(|(name, _)| name))
+                .conflicts_with_all(["prefix", "prefix-any", "ignore-case"])
+                .help("Accept a public key whose leading characters all belong to this character class"))
+            .arg(Arg::new("class-count")
+                .long("class-count")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .default_value("1")
+                .requires("prefix-class")
+                .help("Number of leading characters that must belong to --prefix-class"))
+            .arg(Arg::new("suffix")
+                .long("suffix")
+                .value_name("STR")
+                .help("Base58 suffix the public key should end with, whether it encodes to 43 or 44 characters"))
+            .arg(Arg::new("timeout-minutes")
+                .long("timeout-minutes")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u64))
+                .default_value("3")
+                .help("Give up the keypair search after this many minutes"))
+            .arg(Arg::new("threads")
+                .long("threads")
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Number of worker threads for the keypair search [default: number of CPUs]"))
+            .arg(Arg::new("ignore-case")
+                .long("ignore-case")
+                .action(ArgAction::SetTrue)
+                .help("Match the prefix and suffix case-insensitively; much faster, but the key will not have the exact casing"))
+            .arg(Arg::new("out")
+                .long("out")
+                .value_name("PATH")
+                .help("Write the keypair to a Solana CLI style JSON file"))
+            .arg(Arg::new("force")
+                .long("force")
+                .action(ArgAction::SetTrue)
+                .help("Overwrite the --out file if it already exists"))
+            .arg(Arg::new("show-secret")
+                .long("show-secret")
+                .action(ArgAction::SetTrue)
+                .help("Print the secret key to stdout (it is hidden by default)"))
+            .arg(Arg::new("estimate")
+                .long("estimate")
+                .alias("estimate-vanity")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["out", "show-secret"])
+                .help("Measure the keypair generation rate for a second and print how long the search would take, without searching")))
+        .subcommand(Command::new("send")
+            .about("Send SOL to the wallet address given by --to")
+            .arg(Arg::new("to")
+                .long("to")
+                .value_name("PUBKEY")
+                .required(true)
+                .help("Recipient wallet address"))
+            .arg(sol_amount_arg("Amount to send, e.g. 0.01, 0.01sol or 10000000lamports"))
+            .arg(Arg::new("memo")
+                .long("memo")
+                .value_name("TEXT")
+                .help("Attach this memo to the transfer"))
+            .arg(Arg::new("report")
+                .long("report")
+                .action(ArgAction::SetTrue)
+                .help("After confirmation, fetch the transaction and report the compute units consumed and fee paid")))
+        .subcommand(Command::new("sweep")
+            .about("Send the whole SOL balance, less the transaction fee, to the wallet address given by --to")
+            .arg(Arg::new("to")
+                .long("to")
+                .value_name("PUBKEY")
+                .required(true)
+                .help("Recipient wallet address"))
+            .arg(Arg::new("keep-rent-exempt")
+                .long("keep-rent-exempt")
+                .action(ArgAction::SetTrue)
+                .help("Leave the rent exempt minimum behind so our account stays open")))
+        .subcommand(Command::new("batch-send")
+            .about("Send SOL to every recipient listed in --file, packing as many transfers per transaction as fit")
+            .arg(Arg::new("file")
+                .long("file")
+                .value_name("PATH")
+                .required(true)
+                .help("CSV file of `pubkey,amount` lines, or a .json file of [{\"pubkey\": ..., \"amount\": ...}]; `-` reads stdin")))
+        .subcommand(Command::new("fan-out")
+            .about("Generate new wallets and fund each from ours, to bootstrap tests")
+            .arg(Arg::new("count")
+                .long("count")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u32).range(1..))
+                .required(true)
+                .help("Number of wallets to create"))
+            .arg(sol_amount_arg("Amount to send to each new wallet, e.g. 0.01, 0.01sol or 10000000lamports"))
+            .arg(Arg::new("out")
+                .long("out")
+                .value_name("PATH")
+                .help("Write the keypairs to <out>-0.json, <out>-1.json, ... [default: keypair-<pubkey>.json in --output-dir]"))
+            .arg(Arg::new("force")
+                .long("force")
+                .action(ArgAction::SetTrue)
+                .help("Overwrite keypair files that already exist")))
+        .subcommand(Command::new("submit")
+            .about("Broadcast a transaction signed earlier with --offline")
+            .arg(Arg::new("file")
+                .long("file")
+                .value_name("PATH")
+                .required(true)
+                .help("File with the base64 signed transaction")))
+        .subcommand(Command::new("confirm")
+            .about("Report whether already submitted transactions landed, reading signatures from stdin when none are given")
+            .arg(Arg::new("signatures")
+                .value_name("SIGNATURE")
+                .num_args(1..)
+                .help("Transaction signatures to check")))
+        .subcommand(Command::new("add-signature")
+            .about("Sign a partially signed --offline transaction in --file with the loaded keypair")
+            .arg(Arg::new("file")
+                .long("file")
+                .value_name("PATH")
+                .required(true)
+                .help("File with the base64 transaction, updated in place")))
+        .subcommand(Command::new("create-nonce-account")
+            .about("Create and initialize a durable nonce account")
+            .arg(Arg::new("authority")
+                .long("authority")
+                .value_name("PUBKEY")
+                .help("Authority of the nonce account [default: the loaded keypair's public key]"))
+            .arg(Arg::new("nonce-keypair")
+                .long("nonce-keypair")
+                .value_name("PATH")
+                .help("Use the keypair in this JSON file as the nonce account address [default: a new random keypair]")))
+        .subcommand(Command::new("launch-token")
+            .about("Create a token mint with metadata and mint its initial supply to our own token account")
+            .arg(Arg::new("name")
+                .long("name")
+                .value_name("STR")
+                .required(true)
+                .help("Token name (up to 32 bytes)"))
+            .arg(Arg::new("symbol")
+                .long("symbol")
+                .value_name("STR")
+                .required(true)
+                .help("Token symbol (up to 10 bytes)"))
+            .arg(Arg::new("uri")
+                .long("uri")
+                .value_name("URI")
+                .required(true)
+                .help("URI of the off-chain token metadata JSON (up to 200 bytes)"))
+            .arg(Arg::new("decimals")
+                .long("decimals")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u8))
+                .default_value("2")
+                .help("Number of decimals of the new token mint (0-9)"))
+            .arg(Arg::new("supply")
+                .long("supply")
+                .value_name("AMOUNT")
+                .value_parser(clap::value_parser!(f64))
+                .required(true)
+                .help("Initial supply to mint in major units")))
+        .subcommand(Command::new("create-mint")
+            .about("Create a new token mint")
+            .arg(Arg::new("decimals")
+                .long("decimals")
+                .value_name("N")
+                .value_parser(clap::value_parser!(u8))
+                .default_value("2")
+                .help("Number of decimals of the new token mint (0-9)"))
+            .arg(Arg::new("mint-authority")
+                .long("mint-authority")
+                .value_name("PUBKEY")
+                .help("Mint authority of the new token mint [default: the loaded keypair's public key]"))
+            .arg(Arg::new("freeze-authority")
+                .long("freeze-authority")
+                .value_name("PUBKEY")
+                .help("Freeze authority of the new token mint [default: none]"))
+            .arg(Arg::new("mint-keypair")
+                .long("mint-keypair")
+                .value_name("PATH")
+                .conflicts_with("mint-prefix")
+                .help("Use the keypair in this JSON file as the mint address [default: a new random keypair]"))
+            .arg(Arg::new("mint-prefix")
+                .long("mint-prefix")
+                .value_name("STR")
+                .help("Search for a mint address starting with this base58 prefix before creating the mint")))
+        .subcommand(Command::new("show-mint")
+            .about("Print the supply, decimals and authorities of a token mint")
+            .arg(mint_arg()))
+        .subcommand(Command::new("token-account")
+            .about("Create a new token account")
+            .arg(mint_arg())
+            .arg(Arg::new("owner")
+                .long("owner")
+                .value_name("PUBKEY")
+                .help("Owner of the token account [default: the loaded keypair's public key]")))
+        .subcommand(Command::new("list-token-accounts")
+            .about("List the token accounts of --owner under the selected token program")
+            .arg(Arg::new("owner")
+                .long("owner")
+                .value_name("PUBKEY")
+                .help("Wallet to inspect [default: the loaded keypair's public key]")))
+        .subcommand(Command::new("derive-ata")
+            .about("Print the associated token address of an owner without creating it")
+            .arg(mint_arg())
+            .arg(Arg::new("owner")
+                .long("owner")
+                .value_name("PUBKEY")
+                .help("Owner of the token account [default: the loaded keypair's public key]")))
+        .subcommand(Command::new("find-pda")
+            .about("Derive the program derived address and bump of --program for the given seeds")
+            .arg(Arg::new("program")
+                .long("program")
+                .value_name("PUBKEY")
+                .required(true)
+                .help("Program that owns the address"))
+            .arg(Arg::new("seed")
+                .long("seed")
+                .value_name("SEED")
+                .action(ArgAction::Append)
+                .help("Seed in order: a UTF-8 string, str:TEXT, hex:BYTES or pubkey:PUBKEY, repeat for each seed")))
+        .subcommand(Command::new("mint")
+            .about("Mint --amount tokens to --to (an owner or a token account)")
+            .arg(mint_arg())
+            .arg(Arg::new("to")
+                .long("to")
+                .value_name("PUBKEY")
+                .help("Owner or token account to mint to, an owner's missing token account is created [default: our own token account]"))
+            .arg(amount_arg("Amount of tokens to mint in major units"))
+            .arg(Arg::new("signer")
+                .long("signer")
+                .value_name("KEYPAIR")
+                .action(ArgAction::Append)
+                .help("Keypair file of a multisig mint authority member, repeat for each required signer"))
+            .arg(Arg::new("report")
+                .long("report")
+                .action(ArgAction::SetTrue)
+                .help("After confirmation, fetch the transaction and report the compute units consumed and fee paid")))
+        .subcommand(Command::new("distribute-tokens")
+            .about("Mint, or transfer when we aren't the mint authority, tokens of --mint to every owner listed in --file")
+            .arg(mint_arg())
+            .arg(Arg::new("file")
+                .long("file")
+                .alias("list")
+                .value_name("PATH")
+                .required(true)
+                .help("CSV file of `owner,amount` lines, or a .json file of [{\"pubkey\": ..., \"amount\": ...}]; `-` reads stdin")))
+        .subcommand(Command::new("transfer")
+            .about("Transfer --amount tokens of --mint from our token account to the owner given by --to")
+            .arg(mint_arg())
+            .arg(Arg::new("to")
+                .long("to")
+                .value_name("PUBKEY")
+                .required(true)
+                .help("Owner of the receiving token account"))
+            .arg(amount_arg("Amount of tokens to transfer in major units"))
+            .arg(Arg::new("memo")
+                .long("memo")
+                .value_name("TEXT")
+                .help("Attach this memo to the transfer")))
+        .subcommand(Command::new("burn")
+            .about("Burn --amount tokens of --mint from our token account")
+            .arg(mint_arg())
+            .arg(amount_arg("Amount of tokens to burn in major units")))
+        .subcommand(Command::new("close-account")
+            .about("Close our empty token account for --mint and reclaim its rent")
+            .arg(mint_arg()))
+        .subcommand(Command::new("set-authority")
+            .about("Change or revoke the mint or freeze authority of --mint")
+            .arg(mint_arg())
+            .arg(Arg::new("authority-type")
+                .long("authority-type")
+                .value_name("TYPE")
+                .value_parser(["mint", "freeze"])
+                .required(true)
+                .help("Which authority of the mint to change"))
+            .arg(Arg::new("new-authority")
+                .long("new-authority")
+                .value_name("PUBKEY")
+                .help("New authority of the mint"))
+            .arg(Arg::new("revoke")
+                .long("revoke")
+                .action(ArgAction::SetTrue)
+                .help("Remove the authority for good, e.g. to fix the token supply"))
+            .group(ArgGroup::new("new")
+                .args(["new-authority", "revoke"])
+                .required(true)))
+        .subcommand(Command::new("wrap-sol")
+            .about("Wrap --amount SOL into our wrapped SOL token account")
+            .arg(sol_amount_arg("Amount to wrap, e.g. 0.1, 0.1sol or 100000000lamports")))
+        .subcommand(Command::new("unwrap-sol")
+            .about("Close our wrapped SOL token account and get its SOL back"))
+        .subcommand(Command::new("freeze-account")
+            .about("Freeze a token account of --mint using our freeze authority")
+            .arg(mint_arg())
+            .arg(Arg::new("account")
+                .long("account")
+                .value_name("PUBKEY")
+                .required(true)
+                .help("Token account to freeze")))
+        .subcommand(Command::new("thaw-account")
+            .about("Thaw a frozen token account of --mint using our freeze authority")
+            .arg(mint_arg())
+            .arg(Arg::new("account")
+                .long("account")
+                .value_name("PUBKEY")
+                .required(true)
+                .help("Token account to thaw")))
+        .subcommand(Command::new("create-metadata")
+            .about("Create the token metadata of --mint")
+            .arg(mint_arg())
+            .arg(Arg::new("name")
+                .long("name")
+                .value_name("STR")
+                .default_value("Solana UA Bootcamp 2024-08-06")
+                .help("Token name (up to 32 bytes)"))
+            .arg(Arg::new("symbol")
+                .long("symbol")
+                .value_name("STR")
+                .default_value("UAB-2")
+                .help("Token symbol (up to 10 bytes)"))
+            .arg(Arg::new("uri")
+                .long("uri")
+                .value_name("URI")
+                .default_value("https://arweave.net/1234")
+                .help("URI of the off-chain token metadata JSON (up to 200 bytes)"))
+            .arg(Arg::new("seller-fee-bps")
+                .long("seller-fee-bps")
+                .value_name("BPS")
+                .value_parser(clap::value_parser!(u16))
+                .default_value("0")
+                .help("Royalty in basis points of the token metadata")))
+        .subcommand(Command::new("update-metadata")
+            .about("Update the name, symbol or uri of the token metadata of --mint")
+            .arg(mint_arg())
+            .arg(Arg::new("name")
+                .long("name")
+                .value_name("STR")
+                .help("New token name (up to 32 bytes)"))
+            .arg(Arg::new("symbol")
+                .long("symbol")
+                .value_name("STR")
+                .help("New token symbol (up to 10 bytes)"))
+            .arg(Arg::new("uri")
+                .long("uri")
+                .value_name("URI")
+                .help("New URI of the off-chain token metadata JSON (up to 200 bytes)")))
+        .subcommand(Command::new("delete-metadata")
+            .about("Burn the token metadata of --mint we are the update authority of and reclaim its rent")
+            .arg(mint_arg()))
+        .subcommand(Command::new("show-metadata")
+            .about("Print the Metaplex metadata of a token mint")
+            .arg(mint_arg()))
+}
+
+#[tokio::main]
+async fn main() {
+    let matches = build_cli().get_matches();
+
+    let log_level = match matches.get_count("verbose") {
+        0 => "warn",
+        1 => "info",
+        2 => "debug",
+        _ => "trace",
+    };
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(format!("solana_cli={}", log_level))).init();
+    if let Err(e) = ctrlc::set_handler(on_ctrl_c) {
+        log::warn!("Failed to install the Ctrl-C handler: {}", e);
+    }
+
+    let tool_config = match read_tool_config() {
+        Ok(tool_config) => tool_config,
+        Err(e) => {
+            eprintln!("Reading the config file failed due to: {}", e);
+            std::process::exit(EXIT_BAD_ARGS);
+        }
+    };
+    // Only explicit flags skip the config file defaults.
+    let explicit_keypair = ["keypair", "keystore"].iter().any(|id| matches.contains_id(id));
+    let explicit_rpc_url = ["rpc-url", "cluster"].iter().any(|id| matches.contains_id(id)) || matches.get_flag("auto-rpc");
+    let wallet = match matches.get_one::<String>("wallet").cloned()
+        .or_else(|| tool_config["default_wallet"].as_str().filter(|_| !explicit_keypair).map(str::to_string))
+        .map(|name| resolve_wallet(&tool_config, &name).map(|wallet| (name, wallet)))
+        .transpose()
+    {
+        Ok(wallet) => wallet,
+        Err(e) => {
+            eprintln!("Resolving wallet failed due to: {}", e);
+            std::process::exit(EXIT_BAD_ARGS);
+        }
+    };
+    let endpoint = matches.get_one::<String>("endpoint").cloned()
+        .or_else(|| tool_config["default_endpoint"].as_str().filter(|_| !explicit_rpc_url).map(str::to_string));
+    let rpc_url = match endpoint.map(|name| resolve_endpoint(&tool_config, &name)).unwrap_or_else(|| resolve_rpc_url(
+        matches.get_one::<String>("rpc-url").map(String::as_str),
+        matches.get_one::<String>("cluster").map(String::as_str),
+    )) {
+        Ok(rpc_url) => rpc_url,
+        Err(e) => {
+            eprintln!("Resolving RPC endpoint failed due to: {}", e);
+            std::process::exit(EXIT_BAD_ARGS);
+        }
+    };
+    let (rpc_url, auto_rpc_latency) = match matches.get_flag("auto-rpc") {
+        true => match auto_rpc_candidates(&tool_config, &rpc_url).and_then(|candidates| fastest_endpoint(&candidates)) {
+            Ok((rpc_url, latency)) => (rpc_url, Some(latency)),
+            Err(e) => {
+                eprintln!("Picking an RPC endpoint failed due to: {}", e);
+                std::process::exit(match e {
+                    CliError::InvalidArgument(_) => EXIT_BAD_ARGS,
+                    _ => EXIT_RPC_FAILURE,
+                });
+            }
+        },
+        false => (rpc_url, None),
+    };
+    let (keypair_path, keystore, wallet_secret) = match &wallet {
+        Some((_, Wallet::File(path))) => (Some(path.clone()), None, None),
+        Some((_, Wallet::Keystore(path))) => (None, Some(path.clone()), None),
+        Some((_, Wallet::Secret(secret))) => (None, None, Some(secret.clone())),
+        None => (matches.get_one::<String>("keypair").cloned(), matches.get_one::<String>("keystore").cloned(), None),
+    };
+    let ledger = match matches.get_one::<String>("ledger").map(|path| DerivationPath::from_absolute_path_str(path)).transpose() {
+        Ok(ledger) => ledger,
+        Err(e) => {
+            eprintln!("Parsing the Ledger derivation path failed due to: {}", e);
+            std::process::exit(EXIT_BAD_ARGS);
+        }
+    };
+    let blockhash = match matches.get_one::<String>("blockhash").map(|blockhash| Hash::from_str(blockhash)).transpose() {
+        Ok(blockhash) => blockhash,
+        Err(e) => {
+            eprintln!("Parsing blockhash failed due to: {}", e);
+            std::process::exit(EXIT_BAD_ARGS);
         }
-    } else if matches.get_flag("create-token-mint") {
-        if let Err(e) = create_token_mint() {
-            println!("Creating token mint failed due to: {:?}", e);
+    };
+    let nonce_account = match matches.get_one::<String>("nonce-account").map(|nonce_account| parse_pubkey(nonce_account)).transpose() {
+        Ok(nonce_account) => nonce_account,
+        Err(e) => {
+            eprintln!("Parsing nonce account failed due to: {}", e);
+            std::process::exit(EXIT_BAD_ARGS);
+        }
+    };
+    let (fee_payer, fee_payer_keypair) = match matches.get_one::<String>("fee-payer").map(|fee_payer| parse_fee_payer(fee_payer)).transpose() {
+        Ok(Some((_, None))) if !matches.contains_id("offline") => {
+            eprintln!("A --fee-payer pubkey requires --offline, pass its keypair file to send right away");
+            std::process::exit(EXIT_BAD_ARGS);
         }
-    } else if matches.get_flag("create-token-account") {
-        if let Err(e) = create_token_account() {
-            println!("Creating token account failed due to: {:?}", e);
+        Ok(Some((fee_payer, fee_payer_keypair))) => (Some(fee_payer), fee_payer_keypair),
+        Ok(None) => (None, None),
+        Err(e) => {
+            eprintln!("Loading fee payer failed due to: {}", e);
+            std::process::exit(EXIT_BAD_ARGS);
         }
-    } else if matches.get_flag("mint-tokens") {
-        if let Err(e) = mint_tokens() {
-            println!("Minting tokens failed due to: {:?}", e);
+    };
+    let metadata_program_id = match matches.get_one::<String>("metadata-program-id").map(|program_id| parse_pubkey(program_id)).transpose() {
+        Ok(program_id) => program_id.unwrap_or(mpl_token_metadata::ID),
+        Err(e) => {
+            eprintln!("Parsing metadata program id failed due to: {}", e);
+            std::process::exit(EXIT_BAD_ARGS);
         }
-    } else if matches.get_flag("create-token-metadata") {
-        if let Err(e) = create_token_metadata() {
-            println!("Creating token metadata failed due to: {:?}", e);
+    };
+    let nonce_authority = match matches.get_one::<String>("nonce-authority").map(|path| load_keypair_from_file(path)).transpose() {
+        Ok(nonce_authority) => nonce_authority,
+        Err(e) => {
+            eprintln!("Loading nonce authority failed due to: {}", e);
+            std::process::exit(EXIT_BAD_ARGS);
         }
+    };
+    let config = Config {
+        keypair_path,
+        keystore,
+        wallet: wallet.map(|(name, _)| name),
+        wallet_secret,
+        ledger,
+        rpc_url,
+        token_program_id: if matches.get_flag("token-2022") {
+            spl_token_2022::id()
+        } else {
+            spl_token::id()
+        },
+        json: matches.get_flag("json"),
+        dry_run: matches.get_flag("dry-run"),
+        simulate: matches.get_flag("simulate"),
+        dump_tx: matches.get_flag("dump-tx"),
+        blockhash,
+        offline: matches.get_one::<String>("offline").cloned(),
+        yes: matches.get_flag("yes"),
+        priority_fee: matches.get_one::<u64>("priority-fee").copied(),
+        compute_limit: matches.get_one::<u32>("compute-limit").copied(),
+        nonce_account,
+        nonce_authority,
+        output_dir: matches.get_one::<String>("output-dir").cloned(),
+        websocket: matches.get_flag("websocket"),
+        explorer_links: !matches.get_flag("no-explorer-links"),
+        metadata_program_id,
+        quiet: matches.get_flag("quiet"),
+        fee_payer,
+        fee_payer_keypair,
+        max_fee: *matches.get_one::<u64>("max-fee").unwrap(),
+        max_retries: *matches.get_one::<u32>("max-retries").unwrap(),
+        commitment: CommitmentConfig::from_str(matches.get_one::<String>("commitment").unwrap()).unwrap(),
+    };
+    if let Some(latency) = auto_rpc_latency {
+        config.info(format!("📡 Using {}, the fastest endpoint at {} ms", config.rpc_url, latency.as_millis()));
     }
-}
 
-fn generate_keypair() {
-    let keypair = Keypair::new();
-    println!("The public key is: {}", bs58::encode(keypair.pubkey()).into_string());
-    println!("The secret key is: {:?}", keypair.to_bytes());
-    println!("✅ Finished!");
+    let (action, result) = match matches.subcommand() {
+        Some(("fee-stats", sub_matches)) => {
+            let accounts: Vec<&str> = sub_matches.get_many::<String>("account")
+                .unwrap_or_default()
+                .map(String::as_str)
+                .collect();
+            ("Fetching fee stats", fee_stats(&config, &accounts))
+        }
+        Some(("rpc-info", _)) => ("Checking the RPC endpoint", rpc_info(&config)),
+        Some(("show-config", _)) => ("Showing config", show_config(&config, &matches)),
+        Some(("generate", sub_matches)) => {
+            let count = *sub_matches.get_one::<u32>("count").unwrap();
+            ("Generating keypair", generate_keypairs(&config, &secret_output(sub_matches), count))
+        }
+        Some(("from-mnemonic", sub_matches)) => {
+            let mnemonic = sub_matches.get_one::<String>("mnemonic").map(String::as_str);
+            let passphrase = sub_matches.get_one::<String>("passphrase").unwrap();
+            let derivation_path = sub_matches.get_one::<String>("derivation-path").unwrap();
+            ("Deriving keypair", recover_keypair(&config, mnemonic, passphrase, derivation_path, &secret_output(sub_matches)))
+        }
+        Some(("load", _)) => ("Loading keypair", load_keypair(&config)),
+        Some(("create-keystore", sub_matches)) => {
+            let out = sub_matches.get_one::<String>("out").map(String::as_str);
+            let force = sub_matches.get_flag("force");
+            ("Creating keystore", create_keystore(&config, out, force))
+        }
+        Some(("sign-message", sub_matches)) => {
+            let message = sub_matches.get_one::<String>("message").unwrap();
+            ("Signing message", sign_message(&config, message))
+        }
+        Some(("verify-message", sub_matches)) => {
+            let pubkey = sub_matches.get_one::<String>("pubkey").unwrap();
+            let message = sub_matches.get_one::<String>("message").unwrap();
+            let signature = sub_matches.get_one::<String>("signature").unwrap();
+            ("Verifying message", verify_message(&config, pubkey, message, signature))
+        }
+        Some(("balance", sub_matches)) => {
+            let address = sub_matches.get_one::<String>("address").map(String::as_str);
+            let airdrop_amount = *sub_matches.get_one::<f64>("airdrop-amount").unwrap();
+            let min_balance = *sub_matches.get_one::<f64>("min-balance").unwrap();
+            let airdrop = !sub_matches.get_flag("no-airdrop");
+            let airdrop_timeout = *sub_matches.get_one::<u64>("airdrop-timeout").unwrap();
+            let tokens = sub_matches.get_flag("tokens");
+            (
+                "Checking balance",
+                check_balance(&config, address, airdrop, airdrop_amount, min_balance, airdrop_timeout, tokens).await,
+            )
+        }
+        Some(("check-balances", sub_matches)) => {
+            let file = sub_matches.get_one::<String>("file").unwrap();
+            ("Checking balances", check_balances(&config, file).await)
+        }
+        Some(("airdrop", sub_matches)) => {
+            let amount = *sub_matches.get_one::<u64>("amount").unwrap();
+            let address = sub_matches.get_one::<String>("address").map(String::as_str);
+            let timeout = *sub_matches.get_one::<u64>("timeout").unwrap();
+            ("Requesting airdrop", request_airdrop(&config, address, amount, timeout).await.map(drop))
+        }
+        Some(("check-rent", sub_matches)) => {
+            let address = sub_matches.get_one::<String>("address").unwrap();
+            ("Checking rent exemption", parse_pubkey(address).and_then(|address| check_rent(&config, &address)))
+        }
+        Some(("rent-exemption", sub_matches)) => {
+            let size = *sub_matches.get_one::<usize>("size").unwrap();
+            ("Fetching the rent exempt minimum", rent_exemption_for_size(&config, size))
+        }
+        Some(("history", sub_matches)) => {
+            let address = sub_matches.get_one::<String>("address").map(String::as_str);
+            let limit = *sub_matches.get_one::<usize>("limit").unwrap();
+            let details = sub_matches.get_flag("details");
+            ("Fetching transaction history", show_history(&config, address, limit, details))
+        }
+        Some(("vanity", sub_matches)) => {
+            let suffix = sub_matches.get_one::<String>("suffix").map_or("", String::as_str);
+            let prefix_any: Vec<&str> = sub_matches.get_many::<String>("prefix-any")
+                .unwrap_or_default()
+                .map(String::as_str)
+                .collect();
+            let prefix_class = sub_matches.get_one::<String>("prefix-class")
+                .map(|class| (class.as_str(), *sub_matches.get_one::<usize>("class-count").unwrap()));
+            let prefix = sub_matches.get_one::<String>("prefix").map_or(
+                if suffix.is_empty() && prefix_any.is_empty() && prefix_class.is_none() { "Lev" } else { "" },
+                String::as_str,
+            );
+            let timeout_minutes = *sub_matches.get_one::<u64>("timeout-minutes").unwrap();
+            let threads = sub_matches.get_one::<usize>("threads").copied().unwrap_or_else(num_cpus::get);
+            let ignore_case = sub_matches.get_flag("ignore-case");
+            let estimate = sub_matches.get_flag("estimate");
+            let timeout = Duration::from_secs(if estimate { VANITY_BENCHMARK_SECS } else { timeout_minutes * 60 });
+            let search = VanitySearch::new(prefix, suffix, ignore_case, threads, timeout)
+                .and_then(|search| if prefix_any.is_empty() { Ok(search) } else { search.prefix_any(&prefix_any) })
+                .and_then(|search| match prefix_class {
+                    Some((class, count)) => search.prefix_class(class, count),
+                    None => Ok(search),
+                });
+            if estimate {
+                ("Estimating search time", search.and_then(|search| estimate_vanity_search(&config, &search, timeout_minutes)))
+            } else {
+                (
+                    "Finding keypair",
+                    search.and_then(|search| search_vanity_keypair(&config, &search, timeout_minutes, &secret_output(sub_matches))),
+                )
+            }
+        }
+        Some(("send", sub_matches)) => {
+            let to = sub_matches.get_one::<String>("to").unwrap();
+            let amount = *sub_matches.get_one::<u64>("amount").unwrap();
+            let memo = sub_matches.get_one::<String>("memo").map(String::as_str);
+            let report = sub_matches.get_flag("report");
+            (
+                "Sending SOL",
+                parse_pubkey(to).and_then(|recipient| send_sol(&config, &recipient, amount, memo))
+                    .and_then(|signature| report_transaction_cost(&config, signature.filter(|_| report))),
+            )
+        }
+        Some(("sweep", sub_matches)) => {
+            let to = sub_matches.get_one::<String>("to").unwrap();
+            let keep_rent_exempt = sub_matches.get_flag("keep-rent-exempt");
+            ("Sweeping SOL", parse_pubkey(to).and_then(|recipient| sweep_sol(&config, &recipient, keep_rent_exempt)).map(drop))
+        }
+        Some(("batch-send", sub_matches)) => {
+            let file = sub_matches.get_one::<String>("file").unwrap();
+            ("Sending SOL in batch", batch_send_sol(&config, file).map(drop))
+        }
+        Some(("fan-out", sub_matches)) => {
+            let count = *sub_matches.get_one::<u32>("count").unwrap();
+            let amount = *sub_matches.get_one::<u64>("amount").unwrap();
+            let out = sub_matches.get_one::<String>("out").map(String::as_str);
+            let force = sub_matches.get_flag("force");
+            ("Funding new wallets", fan_out(&config, count, amount, out, force).map(drop))
+        }
+        Some(("submit", sub_matches)) => {
+            let file = sub_matches.get_one::<String>("file").unwrap();
+            ("Submitting transaction", submit_transaction(&config, file).map(drop))
+        }
+        Some(("confirm", sub_matches)) => {
+            let signatures: Vec<&str> = sub_matches.get_many::<String>("signatures")
+                .unwrap_or_default()
+                .map(String::as_str)
+                .collect();
+            ("Checking signatures", confirm_signatures(&config, &signatures))
+        }
+        Some(("add-signature", sub_matches)) => {
+            let file = sub_matches.get_one::<String>("file").unwrap();
+            ("Adding signature", add_signature(&config, file))
+        }
+        Some(("create-nonce-account", sub_matches)) => {
+            let authority = sub_matches.get_one::<String>("authority").map(String::as_str);
+            let nonce_keypair = sub_matches.get_one::<String>("nonce-keypair").map(String::as_str);
+            ("Creating nonce account", create_nonce_account(&config, authority, nonce_keypair).map(drop))
+        }
+        Some(("create-mint", sub_matches)) => {
+            let decimals = *sub_matches.get_one::<u8>("decimals").unwrap();
+            let mint_authority = sub_matches.get_one::<String>("mint-authority").map(String::as_str);
+            let freeze_authority = sub_matches.get_one::<String>("freeze-authority").map(String::as_str);
+            let mint_keypair = sub_matches.get_one::<String>("mint-keypair").map(String::as_str);
+            let mint_prefix = sub_matches.get_one::<String>("mint-prefix").map(String::as_str);
+            ("Creating token mint", create_token_mint(
+                &config,
+                decimals,
+                mint_authority,
+                freeze_authority,
+                mint_keypair,
+                mint_prefix,
+            ).map(drop))
+        }
+        Some(("show-mint", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            ("Fetching token mint", show_mint(&config, mint))
+        }
+        Some(("token-account", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            let owner = sub_matches.get_one::<String>("owner").map(String::as_str);
+            ("Creating token account", create_token_account(&config, mint, owner).map(drop))
+        }
+        Some(("list-token-accounts", sub_matches)) => {
+            let owner = sub_matches.get_one::<String>("owner").map(String::as_str);
+            ("Listing token accounts", list_token_accounts(&config, owner))
+        }
+        Some(("derive-ata", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            let owner = sub_matches.get_one::<String>("owner").map(String::as_str);
+            ("Deriving associated token address", derive_associated_token_address(&config, mint, owner))
+        }
+        Some(("find-pda", sub_matches)) => {
+            let program = sub_matches.get_one::<String>("program").unwrap();
+            let seeds: Vec<&str> = sub_matches.get_many::<String>("seed")
+                .unwrap_or_default()
+                .map(String::as_str)
+                .collect();
+            ("Finding program derived address", find_pda(&config, program, &seeds))
+        }
+        Some(("mint", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            let to = sub_matches.get_one::<String>("to").map(String::as_str);
+            let amount = *sub_matches.get_one::<f64>("amount").unwrap();
+            let signers: Vec<&str> = sub_matches.get_many::<String>("signer")
+                .unwrap_or_default()
+                .map(String::as_str)
+                .collect();
+            let report = sub_matches.get_flag("report");
+            (
+                "Minting tokens",
+                mint_tokens(&config, mint, to, amount, &signers)
+                    .and_then(|signature| report_transaction_cost(&config, signature.filter(|_| report))),
+            )
+        }
+        Some(("distribute-tokens", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            let file = sub_matches.get_one::<String>("file").unwrap();
+            ("Distributing tokens", distribute_tokens(&config, mint, file).map(drop))
+        }
+        Some(("transfer", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            let to = sub_matches.get_one::<String>("to").unwrap();
+            let amount = *sub_matches.get_one::<f64>("amount").unwrap();
+            let memo = sub_matches.get_one::<String>("memo").map(String::as_str);
+            ("Transferring tokens", transfer_tokens(&config, mint, to, amount, memo).map(drop))
+        }
+        Some(("burn", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            let amount = *sub_matches.get_one::<f64>("amount").unwrap();
+            ("Burning tokens", burn_tokens(&config, mint, amount).map(drop))
+        }
+        Some(("close-account", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            ("Closing token account", close_token_account(&config, mint).map(drop))
+        }
+        Some(("set-authority", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            let authority_type = sub_matches.get_one::<String>("authority-type").unwrap();
+            let new_authority = sub_matches.get_one::<String>("new-authority").map(String::as_str);
+            ("Setting authority", set_mint_authority(&config, mint, authority_type, new_authority).map(drop))
+        }
+        Some(("wrap-sol", sub_matches)) => {
+            let amount = *sub_matches.get_one::<u64>("amount").unwrap();
+            ("Wrapping SOL", wrap_sol(&config, amount).map(drop))
+        }
+        Some(("unwrap-sol", _)) => ("Unwrapping SOL", unwrap_sol(&config).map(drop)),
+        Some(("freeze-account", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            let account = sub_matches.get_one::<String>("account").unwrap();
+            ("Freezing token account", set_account_frozen(&config, mint, account, true).map(drop))
+        }
+        Some(("thaw-account", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            let account = sub_matches.get_one::<String>("account").unwrap();
+            ("Thawing token account", set_account_frozen(&config, mint, account, false).map(drop))
+        }
+        Some(("launch-token", sub_matches)) => {
+            let name = sub_matches.get_one::<String>("name").unwrap();
+            let symbol = sub_matches.get_one::<String>("symbol").unwrap();
+            let uri = sub_matches.get_one::<String>("uri").unwrap();
+            let decimals = *sub_matches.get_one::<u8>("decimals").unwrap();
+            let supply = *sub_matches.get_one::<f64>("supply").unwrap();
+            ("Launching token", launch_token(&config, name, symbol, uri, decimals, supply).map(drop))
+        }
+        Some(("create-metadata", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            let name = sub_matches.get_one::<String>("name").unwrap();
+            let symbol = sub_matches.get_one::<String>("symbol").unwrap();
+            let uri = sub_matches.get_one::<String>("uri").unwrap();
+            let seller_fee_bps = *sub_matches.get_one::<u16>("seller-fee-bps").unwrap();
+            ("Creating token metadata", create_token_metadata(&config, mint, name, symbol, uri, seller_fee_bps).map(drop))
+        }
+        Some(("update-metadata", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            let name = sub_matches.get_one::<String>("name").map(String::as_str);
+            let symbol = sub_matches.get_one::<String>("symbol").map(String::as_str);
+            let uri = sub_matches.get_one::<String>("uri").map(String::as_str);
+            ("Updating token metadata", update_token_metadata(&config, mint, name, symbol, uri).map(drop))
+        }
+        Some(("delete-metadata", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            ("Deleting token metadata", delete_token_metadata(&config, mint).map(drop))
+        }
+        Some(("show-metadata", sub_matches)) => {
+            let mint = sub_matches.get_one::<String>("mint").map(String::as_str);
+            ("Fetching token metadata", show_token_metadata(&config, mint))
+        }
+        _ => unreachable!("clap requires a subcommand"),
+    };
+
+    // Errors go to stderr in both modes, so stdout only ever carries results.
+    if let Err(e) = result {
+        if config.json {
+            eprintln!("{}", json!({ "error": format!("{} failed due to: {}", action, e) }));
+        } else {
+            eprintln!("{} failed due to: {}", action, e);
+        }
+        std::process::exit(exit_code(&e));
+    }
 }
 
-fn load_keypair_from_env() -> Keypair {
-    dotenv().expect(".env file not found");
-    let private_key = env::var("SECRET_KEY").expect("Add SECRET_KEY to .env!");
-    let as_array: Vec<u8> = serde_json::from_str(&private_key)
-        .expect("Failed to parse SECRET_KEY from .env");
-    Keypair::from_bytes(&as_array).expect("Failed to create Keypair from secret key")
+// The first QUIET_FIELDS value of a result; lists and other results are printed in full.
+fn quiet_output(json: &serde_json::Value) -> Option<String> {
+    QUIET_FIELDS.iter().find_map(|field| match json.get(field)? {
+        serde_json::Value::String(value) => Some(value.clone()),
+        serde_json::Value::Number(value) => Some(value.to_string()),
+        _ => None,
+    })
 }
 
-fn load_keypair() {
-    let keypair = load_keypair_from_env();
-    println!("Public key: {}", bs58::encode(keypair.pubkey()).into_string());
+// Ctrl-C exits right away, unless a search or wait is running that can stop cleanly and report
+// how far it got. A second Ctrl-C always exits.
+fn on_ctrl_c() {
+    if !INTERRUPTIBLE.load(Ordering::Relaxed) || INTERRUPTED.swap(true, Ordering::Relaxed) {
+        std::process::exit(EXIT_INTERRUPTED);
+    }
+    eprintln!("\n🛑 Stopping, press Ctrl-C again to quit right away");
 }
 
-fn create_connection() -> RpcClient {
-    RpcClient::new_with_commitment(
-        "https://api.devnet.solana.com".to_string(),
-        CommitmentConfig::confirmed(),
-    )
+fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::Relaxed)
 }
 
-async fn check_balance() {
-    let connection = create_connection();
-    println!("⚡️ Connected to devnet");
-    let public_key = Pubkey::from_str("8cUNp6LJGfjN3M1mwk537CfY2WBtYUYQNnf4hVtPx7AB").unwrap();
-    
-    if let Err(e) = airdrop_if_required(&connection, &public_key, 0.5, 1.5).await {
-        println!("Airdrop failed due to: {:?}", e);
+// Lets Ctrl-C stop the running search or wait for as long as it is alive.
+struct Interruptible;
+
+impl Interruptible {
+    fn start() -> Self {
+        INTERRUPTIBLE.store(true, Ordering::Relaxed);
+        Interruptible
     }
-    
-    let balance_in_lamports = connection.get_balance(&public_key).unwrap();
-    let balance_in_sol = balance_in_lamports as f64 / LAMPORTS_PER_SOL as f64;
-    println!(
-        "💰 The balance for the wallet at address {} is: {} SOL",
-        public_key, balance_in_sol
-    );
 }
 
-async fn airdrop_if_required(
-    connection: &RpcClient,
-    public_key: &Pubkey,
-    airdrop_amount: f64,
-    min_balance: f64,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let current_balance = connection.get_balance(public_key)?;
-    if current_balance < (min_balance * LAMPORTS_PER_SOL as f64) as u64 {
-        println!("Requesting airdrop...");
+impl Drop for Interruptible {
+    fn drop(&mut self) {
+        INTERRUPTIBLE.store(false, Ordering::Relaxed);
+    }
+}
 
-        let signature = connection
-            .request_airdrop(public_key, (airdrop_amount * LAMPORTS_PER_SOL as f64) as u64)?;
+// Every failure a command can end with; the variant decides the exit code.
+#[derive(Debug)]
+enum CliError {
+    InvalidPubkey { value: String, reason: String },
+    InvalidArgument(String),
+    Rpc(ClientError),
+    InsufficientFunds { needed: u64, available: u64 },
+    InsufficientTokens(String),
+    KeypairLoad(String),
+    Timeout { signature: Signature, seconds: u64 },
+    TransactionFailed(String),
+    SignatureMismatch { pubkey: Pubkey },
+    Interrupted(String),
+    Io(std::io::Error),
+    Other(String),
+}
 
-        loop {
-            let commitment_config = CommitmentConfig::processed();
-            let confirmed = connection.confirm_transaction_with_commitment(&signature, commitment_config)?;
-            if confirmed.value {
-                break;
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            CliError::InvalidPubkey { value, reason } => write!(f, "Invalid public key '{}': {}", value, reason),
+            CliError::Rpc(e) => write!(f, "{}", e),
+            CliError::InsufficientFunds { needed, available } => write!(
+                f,
+                "insufficient funds: need {} SOL (amount, fee and rent exempt minimum), have {} SOL",
+                *needed as f64 / LAMPORTS_PER_SOL as f64,
+                *available as f64 / LAMPORTS_PER_SOL as f64
+            ),
+            CliError::Timeout { signature, seconds } => {
+                write!(f, "Transaction {} was not confirmed within {} seconds", signature, seconds)
             }
+            CliError::SignatureMismatch { pubkey } => write!(f, "The signature was not made by {}", pubkey),
+            CliError::Io(e) => write!(f, "{}", e),
+            CliError::InvalidArgument(message)
+            | CliError::InsufficientTokens(message)
+            | CliError::KeypairLoad(message)
+            | CliError::TransactionFailed(message)
+            | CliError::Interrupted(message)
+            | CliError::Other(message) => write!(f, "{}", message),
         }
+    }
+}
 
-        println!("Airdrop complete");
-    } else {
-        println!("No airdrop required");
+impl std::error::Error for CliError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CliError::Rpc(e) => Some(e),
+            CliError::Io(e) => Some(e),
+            _ => None,
+        }
     }
-    Ok(())
 }
 
-fn find_keypair(prefix: &str, max_minutes: u64) {
-    let start_time = Instant::now();
-    let max_duration = Duration::from_secs(max_minutes * 60);
+impl From<ClientError> for CliError {
+    fn from(e: ClientError) -> Self {
+        CliError::Rpc(e)
+    }
+}
 
-    loop {
-        if start_time.elapsed() > max_duration {
-            println!("⏰ Time out! The public key starting with '{}' was not found within {} minutes.", prefix, max_minutes);
-            break;
-        }
-        let keypair = Keypair::new();
-        let public_key_base58 = bs58::encode(keypair.pubkey()).into_string();
+impl From<std::io::Error> for CliError {
+    fn from(e: std::io::Error) -> Self {
+        CliError::Io(e)
+    }
+}
 
-        if public_key_base58.starts_with(prefix) {
-            let elapsed_time = start_time.elapsed();
-            println!("⌛ Found matching keypair in {} second(s) or {:.2} minute(s)!",
-                elapsed_time.as_secs(),
-                elapsed_time.as_secs_f64() / 60.0
-            );
-            println!("The public key is: {}", public_key_base58);
-            println!("The secret key is: {:?}", keypair.to_bytes());
-            println!("✅ Finished!");
-            break;
-        }
+impl From<String> for CliError {
+    fn from(message: String) -> Self {
+        CliError::Other(message)
     }
 }
 
-fn send_sol() -> Result<(), Box<dyn std::error::Error>> {
-    let sender = load_keypair_from_env();
- 
-    let connection = create_connection();
-    println!("🔑 Our public key is: {}", sender.pubkey());
+impl From<&str> for CliError {
+    fn from(message: &str) -> Self {
+        CliError::Other(message.to_string())
+    }
+}
 
-    let recipient = Pubkey::from_str("8cUNp6LJGfjN3M1mwk537CfY2WBtYUYQNnf4hVtPx7AB").unwrap();
-    println!("💸 Attempting to send 0.01 SOL to {}...", recipient);
+// Foreign errors that only ever get reported, keeping their message.
+macro_rules! cli_error_from {
+    ($variant:ident: $($error:ty),+) => {
+        $(impl From<$error> for CliError {
+            fn from(e: $error) -> Self {
+                CliError::$variant(e.to_string())
+            }
+        })+
+    };
+}
 
-    let transfer_instruction = system_instruction::transfer(&sender.pubkey(), &recipient, (0.01 * LAMPORTS_PER_SOL as f64) as u64);
+cli_error_from!(InvalidArgument: std::num::ParseIntError, solana_sdk::pubkey::ParsePubkeyError, solana_sdk::signature::ParseSignatureError);
+cli_error_from!(
+    Other: serde_json::Error,
+    bincode::Error,
+    solana_sdk::program_error::ProgramError,
+    solana_sdk::signature::SignerError,
+    solana_remote_wallet::locator::LocatorError,
+    tokio::task::JoinError,
+    tokio::sync::AcquireError
+);
 
-    let memo_program_id = Pubkey::from_str("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr")?;
-    let memo_text = "Hello from Solana!";
-    let memo_instruction = solana_sdk::instruction::Instruction::new_with_bytes(
-        memo_program_id,
-        memo_text.as_bytes(),
-        vec![],
-    );
+// Sorts errors into the exit codes scripts can branch on; clap itself exits with 2 on bad usage.
+fn exit_code(e: &CliError) -> i32 {
+    match e {
+        CliError::InvalidPubkey { .. } | CliError::InvalidArgument(_) => EXIT_BAD_ARGS,
+        CliError::Rpc(e) if rpc_insufficient_funds(e) => EXIT_INSUFFICIENT_FUNDS,
+        CliError::Rpc(_) | CliError::Timeout { .. } => EXIT_RPC_FAILURE,
+        CliError::InsufficientFunds { .. } | CliError::InsufficientTokens(_) => EXIT_INSUFFICIENT_FUNDS,
+        CliError::KeypairLoad(_)
+        | CliError::TransactionFailed(_)
+        | CliError::SignatureMismatch { .. }
+        | CliError::Io(_)
+        | CliError::Other(_) => EXIT_FAILURE,
+        CliError::Interrupted(_) => EXIT_INTERRUPTED,
+    }
+}
 
-    let mut transaction = Transaction::new_with_payer(
-        &[transfer_instruction, memo_instruction],
-        Some(&sender.pubkey()),
-    );
+// The node refusing a transaction the fee payer can't cover is a funds problem, not an RPC one.
+fn rpc_insufficient_funds(e: &ClientError) -> bool {
+    matches!(
+        e.get_transaction_error(),
+        Some(TransactionError::InsufficientFundsForFee | TransactionError::InsufficientFundsForRent { .. })
+    )
+}
 
-    println!("📝 memo is: {}", memo_text);
-    
-    let recent_blockhash = connection.get_latest_blockhash()?;
-    transaction.sign(&[&sender], recent_blockhash);
+fn cluster_name(rpc_url: &str) -> &str {
+    match rpc_url.trim_end_matches('/') {
+        DEVNET_RPC_URL => "devnet",
+        "https://api.testnet.solana.com" => "testnet",
+        "https://api.mainnet-beta.solana.com" => "mainnet-beta",
+        "http://localhost:8899" => "localhost",
+        _ => "custom",
+    }
+}
 
-    let signature = connection.send_and_confirm_transaction_with_spinner_and_commitment(
-        &transaction,
-        CommitmentConfig::processed(),
-    )?;
+// Asks the RPC endpoint for its health, software version and current slot.
+fn rpc_info(config: &Config) -> Result<(), CliError> {
+    let connection = create_connection(config);
 
-    println!("✅ Transaction confirmed, signature: {}!", signature);
-    
+    // An unhealthy node still answers the other calls, so report it instead of failing.
+    let health = connection.get_health().err().map(|e| e.to_string());
+    let version = with_retry(config.max_retries, "getVersion", || connection.get_version())?;
+    let slot = with_retry(config.max_retries, "getSlot", || connection.get_slot())?;
+
+    config.output(
+        format!(
+            "🩺 RPC endpoint {} ({}):\n  Health: {}\n  Version: {}\n  Slot: {}",
+            config.rpc_url,
+            cluster_name(&config.rpc_url),
+            health.as_deref().map_or("ok".to_string(), |e| format!("unhealthy ({})", e)),
+            version.solana_core,
+            slot,
+        ),
+        json!({
+            "rpc_url": config.rpc_url,
+            "healthy": health.is_none(),
+            "health_error": health,
+            "version": version.solana_core,
+            "feature_set": version.feature_set,
+            "slot": slot,
+        }),
+    );
     Ok(())
 }
 
-fn create_token_mint() -> Result<(), Box<dyn std::error::Error>> {
-    let sender = load_keypair_from_env();
- 
-    let connection = create_connection();
-    println!("🔑 Our public key is: {}", sender.pubkey());
+const FEE_PERCENTILES: [u8; 5] = [25, 50, 75, 90, 100];
 
-    let mint_pubkey = create_mint(
-        &connection,
-        &sender,
-        &sender.pubkey(),
-        None,
-        2,
-    )?;
-    
-    let explorer_link = format!(
-        "https://explorer.solana.com/address/{}?cluster=devnet",
-        mint_pubkey
-    );
+// Nearest-rank percentiles of the fees; None when there are no samples.
+fn fee_percentiles(mut fees: Vec<u64>) -> Option<Vec<(u8, u64)>> {
+    if fees.is_empty() {
+        return None;
+    }
+    fees.sort_unstable();
+    Some(FEE_PERCENTILES.iter().map(|&percentile| {
+        let rank = (percentile as usize * fees.len()).div_ceil(100).max(1);
+        (percentile, fees[rank - 1])
+    }).collect())
+}
+
+fn fee_stats(config: &Config, accounts: &[&str]) -> Result<(), CliError> {
+    let accounts = accounts.iter().map(|account| parse_pubkey(account)).collect::<Result<Vec<_>, _>>()?;
 
-    println!("✅ Token Mint: {}", explorer_link);
+    let connection = create_connection(config);
+    config.info(format!("⚡️ Connected to {}", config.rpc_url));
 
+    let recent_fees = with_retry(config.max_retries, "getRecentPrioritizationFees", || {
+        connection.get_recent_prioritization_fees(&accounts)
+    })?;
+    let slots = recent_fees.len();
+    let percentiles = fee_percentiles(recent_fees.into_iter().map(|fee| fee.prioritization_fee).collect())
+        .ok_or("The RPC returned no recent prioritization fees")?;
+
+    let mut human = format!("📈 Priority fees over the last {} slot(s), in micro-lamports per compute unit:", slots);
+    for (percentile, fee) in &percentiles {
+        let label = if *percentile == 100 { "max".to_string() } else { format!("p{}", percentile) };
+        human.push_str(&format!("\n  {:<4} {}", label, fee));
+    }
+    human.push_str("\n💡 Pass the p50 or p75 value as --priority-fee to land ahead of most transactions");
+    config.output(
+        human,
+        json!({
+            "slots": slots,
+            "accounts": accounts.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+            "percentiles": percentiles.iter()
+                .map(|(percentile, fee)| (format!("p{}", percentile), json!(fee)))
+                .collect::<serde_json::Map<_, _>>(),
+        }),
+    );
     Ok(())
 }
 
-fn create_mint(
-    connection: &RpcClient,
-    payer: &Keypair,
-    mint_authority: &Pubkey,
-    freeze_authority: Option<&Pubkey>,
-    decimals: u8,
-) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    let mint_account = Keypair::new();
-    let mint_pubkey = mint_account.pubkey();
-    let mint_rent_exempt_balance = connection.get_minimum_balance_for_rent_exemption(Mint::LEN)?;
+// Reports the resolved settings without touching the network; a keystore is not decrypted,
+// its pubkey is read from the file.
+fn show_config(config: &Config, matches: &clap::ArgMatches) -> Result<(), CliError> {
+    let source = |id: &str| match matches.value_source(id) {
+        Some(ValueSource::CommandLine) => format!("--{}", id),
+        Some(ValueSource::EnvVariable) => "env".to_string(),
+        _ => "default".to_string(),
+    };
 
-    let create_account_instruction = solana_sdk::system_instruction::create_account(
-        &payer.pubkey(),
-        &mint_pubkey,
-        mint_rent_exempt_balance,
-        Mint::LEN as u64,
-        &spl_token::id(),
-    );
+    let rpc_url_source = match (matches.value_source("rpc-url"), matches.value_source("cluster"), matches.value_source("endpoint")) {
+        _ if matches.get_flag("auto-rpc") => "--auto-rpc".to_string(),
+        (Some(ValueSource::CommandLine), _, _) => source("rpc-url"),
+        (_, Some(ValueSource::CommandLine), _) => source("cluster"),
+        (_, _, Some(ValueSource::CommandLine)) => format!("--endpoint {}", matches.get_one::<String>("endpoint").unwrap()),
+        _ if read_tool_config().is_ok_and(|tool_config| tool_config["default_endpoint"].is_string()) => {
+            "config file default_endpoint".to_string()
+        }
+        _ => "default".to_string(),
+    };
+    let wallet = config.wallet.as_ref().map_or(String::new(), |name| format!("wallet '{}', ", name));
 
-    let mint_instruction = initialize_mint(
-        &spl_token::id(),
-        &mint_pubkey,
-        mint_authority,
-        freeze_authority,
-        decimals,
-    )?;
+    let (keypair_source, pubkey) = match (&config.keypair_path, &config.keystore) {
+        // Reading the pubkey would need the device, so it isn't touched here.
+        _ if config.ledger.is_some() => (
+            format!("--ledger {}", config.ledger.as_ref().map(|path| format!("{:?}", path)).unwrap_or_default()),
+            Err("read from the device when signing".into()),
+        ),
+        (Some(path), _) => (format!("{}--keypair {}", wallet, path), load_keypair_from_file(path).map(|keypair| keypair.pubkey().to_string())),
+        (None, Some(path)) => (
+            format!("{}--keystore {}", wallet, path),
+            fs::read_to_string(path)
+                .map_err(|e| e.into())
+                .and_then(|contents| serde_json::from_str::<serde_json::Value>(&contents).map_err(|e| e.into()))
+                .and_then(|keystore| keystore["pubkey"].as_str().map(str::to_string).ok_or_else(|| "no pubkey in the keystore".into())),
+        ),
+        (None, None) if config.wallet_secret.is_some() => (format!("{}inline secret", wallet), load_signer(config).map(|keypair| keypair.pubkey().to_string())),
+        (None, None) => {
+            let source = if env::var("SECRET_KEY").is_ok() { "SECRET_KEY env" } else { ".env SECRET_KEY" };
+            (source.to_string(), load_keypair_from_env().map(|keypair| keypair.pubkey().to_string()))
+        }
+    };
+    let pubkey = pubkey.unwrap_or_else(|e| format!("unavailable ({})", e));
 
-    let transaction = Transaction::new_signed_with_payer(
-        &[create_account_instruction, mint_instruction],
-        Some(&payer.pubkey()),
-        &[payer, &mint_account],
-        connection.get_latest_blockhash()?,
-    );
+    let token_program = if config.token_program_id == spl_token_2022::id() { "token-2022" } else { "token" };
 
-    connection.send_and_confirm_transaction(&transaction)?;
+    config.output(
+        format!(
+            "⚙️ Config:\n  RPC URL: {} ({})\n  Cluster: {}\n  Keypair: {} ({})\n  Commitment: {:?} ({})\n  Token program: {} ({})",
+            config.rpc_url,
+            rpc_url_source,
+            cluster_name(&config.rpc_url),
+            pubkey,
+            keypair_source,
+            config.commitment.commitment,
+            source("commitment"),
+            token_program,
+            source("token-2022"),
+        ),
+        json!({
+            "rpc_url": { "value": config.rpc_url, "source": rpc_url_source },
+            "cluster": cluster_name(&config.rpc_url),
+            "keypair": { "pubkey": pubkey, "source": keypair_source },
+            "commitment": { "value": format!("{:?}", config.commitment.commitment), "source": source("commitment") },
+            "token_program": { "value": config.token_program_id.to_string(), "source": source("token-2022") },
+        }),
+    );
+    Ok(())
+}
 
-    Ok(mint_pubkey)
+struct SecretOutput<'a> {
+    out: Option<&'a str>,
+    force: bool,
+    show_secret: bool,
 }
 
-fn create_token_account() -> Result<(), Box<dyn std::error::Error>> {
-    let sender = load_keypair_from_env();
- 
-    let connection = create_connection();
-    println!("🔑 Our public key is: {}", sender.pubkey());
+fn secret_output(matches: &clap::ArgMatches) -> SecretOutput<'_> {
+    SecretOutput {
+        out: matches.get_one::<String>("out").map(String::as_str),
+        force: matches.get_flag("force"),
+        show_secret: matches.get_flag("show-secret"),
+    }
+}
 
-    let token_mint_account = Pubkey::from_str("ExJmrjcJj3FuHNvswLkLmAxiEBGcdW5g9WnZqb8VjCiz").unwrap();
-    let recipient = Pubkey::from_str("8cUNp6LJGfjN3M1mwk537CfY2WBtYUYQNnf4hVtPx7AB").unwrap();
+// Saves the keypair to --out and reveals the secret only when asked to, returning the
+// human-readable lines and the JSON fields describing the keypair.
+fn report_keypair(
+    config: &Config,
+    keypair: &Keypair,
+    secret_output: &SecretOutput,
+) -> Result<(String, serde_json::Value), CliError> {
+    let public_key = bs58::encode(keypair.pubkey()).into_string();
+    let mut human = format!("The public key is: {}", public_key);
+    let mut json = json!({ "pubkey": public_key });
 
-    let account_pubkey = get_or_create_associated_token_account(&connection, &sender, &token_mint_account, &recipient)?;
+    let path = match secret_output.out {
+        Some(path) => Some(config.output_file(path)?),
+        None => config.default_output_file("keypair", &keypair.pubkey())?,
+    };
+    if let Some(path) = &path {
+        write_keypair_file(keypair, path, secret_output.force)?;
+        human.push_str(&format!("\n💾 Keypair written to {}", path));
+        json["file"] = json!(path);
+    }
+    if secret_output.show_secret {
+        let secret_key_base58 = bs58::encode(keypair.to_bytes()).into_string();
+        let secret_key_array = serde_json::to_string(&keypair.to_bytes().to_vec())?;
+        human.push_str(&format!("\nThe secret key (base58) is: {}", secret_key_base58));
+        human.push_str(&format!("\nThe secret key (JSON array) is: {}", secret_key_array));
+        json["secret_key"] = json!(keypair.to_bytes().to_vec());
+        json["secret_key_base58"] = json!(secret_key_base58);
+    } else if path.is_none() {
+        human.push_str("\n🔒 The secret key is hidden, pass --out <PATH> to save it or --show-secret to print it");
+    }
 
-    println!("Token Account: {}", account_pubkey);
+    Ok((human, json))
+}
 
-    let explorer_link = format!(
-        "https://explorer.solana.com/address/{}?cluster=devnet",
-        account_pubkey
-    );
+fn generate_keypairs(config: &Config, secret_output: &SecretOutput, count: u32) -> Result<(), CliError> {
+    if count == 1 {
+        let keypair = Keypair::new();
+        let (human, json) = report_keypair(config, &keypair, secret_output)?;
+        config.output(format!("{}\n✅ Finished!", human), json);
+        return Ok(());
+    }
 
-    println!("✅ Created token account: {}", explorer_link);
+    // Check every numbered file up front so we don't stop halfway through the batch.
+    let paths: Vec<Option<String>> = (0..count)
+        .map(|index| secret_output.out.map(|path| numbered_path(path, index)))
+        .collect();
+    if let Some(path) = paths.iter().flatten()
+        .map(|path| config.output_file(path))
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .find(|path| !secret_output.force && Path::new(path).exists())
+    {
+        return Err(format!("{} already exists, pass --force to overwrite it", path).into());
+    }
 
+    let mut human = vec![];
+    let mut json = vec![];
+    for path in &paths {
+        let keypair = Keypair::new();
+        let (keypair_human, keypair_json) = report_keypair(
+            config,
+            &keypair,
+            &SecretOutput { out: path.as_deref(), ..*secret_output },
+        )?;
+        human.push(keypair_human);
+        json.push(keypair_json);
+    }
+    config.output(format!("{}\n✅ Finished!", human.join("\n")), json!(json));
     Ok(())
 }
 
-fn get_or_create_associated_token_account(
-    connection: &RpcClient,
-    sender: &Keypair,
-    mint: &Pubkey,
-    recipient: &Pubkey,
-) -> Result<Pubkey, Box<dyn std::error::Error>> {
-    let associated_token_address = get_associated_token_address(recipient, mint);
+// Turns keys.json into keys-0.json, keys-1.json, ...
+fn numbered_path(path: &str, index: u32) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, index, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, index),
+    };
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
 
-    if connection.get_account(&associated_token_address).is_err() {
-        let create_ata_instruction = create_associated_token_account(
-            &sender.pubkey(),
-            recipient,
-            mint,
-            &spl_token::id(),
-        );
+fn create_secret_file(path: &str, force: bool) -> Result<fs::File, CliError> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
 
-        let transaction = Transaction::new_signed_with_payer(
-            &[create_ata_instruction],
-            Some(&sender.pubkey()),
-            &[sender],
-            connection.get_latest_blockhash()?,
-        );
+    options.open(path).map_err(|e| {
+        if e.kind() == std::io::ErrorKind::AlreadyExists {
+            format!("{} already exists, pass --force to overwrite it", path).into()
+        } else {
+            format!("Failed to open {}: {}", path, e).into()
+        }
+    })
+}
+
+fn write_keypair_file(keypair: &Keypair, path: &str, force: bool) -> Result<(), CliError> {
+    let mut file = create_secret_file(path, force)?;
+    file.write_all(serde_json::to_string(&keypair.to_bytes().to_vec())?.as_bytes())?;
+    Ok(())
+}
 
-        connection.send_and_confirm_transaction(&transaction)?;
+// Keeps keypairs the CLI made up for new accounts in --output-dir so they aren't lost.
+fn save_generated_keypair(config: &Config, kind: &str, keypair: Keypair) -> Result<Keypair, CliError> {
+    if let Some(path) = config.default_output_file(kind, &keypair.pubkey())? {
+        write_keypair_file(&keypair, &path, false)?;
+        config.info(format!("💾 Saved the {} keypair to {}", kind, path));
     }
+    Ok(keypair)
+}
 
-    Ok(associated_token_address)
+fn keystore_key(passphrase: &str, salt: &[u8]) -> Result<Key, CliError> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| format!("Failed to derive the keystore key: {}", e))?;
+    Ok(key)
 }
 
-fn mint_tokens() -> Result<(), Box<dyn std::error::Error>> {
-    let sender = load_keypair_from_env();
+fn encrypt_keypair(keypair: &Keypair, passphrase: &str) -> Result<serde_json::Value, CliError> {
+    let mut salt = [0u8; KEYSTORE_SALT_BYTES];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; KEYSTORE_NONCE_BYTES];
+    OsRng.fill_bytes(&mut nonce);
 
-    let connection = create_connection();
-    
-    const MINOR_UNITS_PER_MAJOR_UNITS: u64 = 10_u64.pow(2);
+    let cipher = ChaCha20Poly1305::new(&keystore_key(passphrase, &salt)?);
+    let ciphertext = cipher.encrypt(&Nonce::from(nonce), keypair.to_bytes().as_ref())
+        .map_err(|_| "Failed to encrypt the keypair")?;
 
-    let token_mint_account = Pubkey::from_str("ExJmrjcJj3FuHNvswLkLmAxiEBGcdW5g9WnZqb8VjCiz").unwrap();
+    Ok(json!({
+        "version": 1,
+        "kdf": "argon2id",
+        "cipher": "chacha20poly1305",
+        "pubkey": keypair.pubkey().to_string(),
+        "salt": BASE64.encode(salt),
+        "nonce": BASE64.encode(nonce),
+        "ciphertext": BASE64.encode(ciphertext),
+    }))
+}
 
-    let recipient_associated_token_account = Pubkey::from_str("CtWYrszfioSrDA8G9GTGMmwjcs1J6LFzTVkkByT5daYy").unwrap();
+fn decrypt_keypair(keystore: &serde_json::Value, passphrase: &str) -> Result<Keypair, CliError> {
+    if keystore["version"] != 1 {
+        return Err(format!("Unsupported keystore version {}", keystore["version"]).into());
+    }
+    let field = |name: &str| -> Result<Vec<u8>, CliError> {
+        let value = keystore[name].as_str().ok_or_else(|| format!("Keystore is missing {}", name))?;
+        BASE64.decode(value).map_err(|e| CliError::InvalidArgument(format!("Invalid keystore {}: {}", name, e)))
+    };
+    let salt = field("salt")?;
+    let nonce: [u8; KEYSTORE_NONCE_BYTES] = field("nonce")?
+        .try_into()
+        .map_err(|_| CliError::InvalidArgument(format!("Invalid keystore nonce, expected {} bytes", KEYSTORE_NONCE_BYTES)))?;
 
+    let cipher = ChaCha20Poly1305::new(&keystore_key(passphrase, &salt)?);
+    let secret = cipher.decrypt(&Nonce::from(nonce), field("ciphertext")?.as_ref())
+        .map_err(|_| "Failed to decrypt the keystore, wrong passphrase?")?;
+    Keypair::from_bytes(&secret)
+        .map_err(|e| format!("Failed to create Keypair from the keystore: {}", e).into())
+}
+
+fn read_passphrase(prompt: &str) -> Result<String, CliError> {
+    if let Ok(passphrase) = env::var("SOLANA_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    rpassword::prompt_password(prompt)
+        .map_err(|e| format!("Failed to read the passphrase, set SOLANA_PASSPHRASE instead: {}", e).into())
+}
+
+fn load_keypair_from_keystore(path: &str) -> Result<Keypair, CliError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read keystore {}: {}", path, e))?;
+    let keystore: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse keystore {}: {}", path, e))?;
+    let passphrase = read_passphrase(&format!("Passphrase for {}: ", path))?;
+    decrypt_keypair(&keystore, &passphrase)
+}
+
+fn create_keystore(config: &Config, out: Option<&str>, force: bool) -> Result<(), CliError> {
+    // Only a keypair on disk can be exported, never a --ledger key.
+    let keypair = load_keypair_from_config(config)?;
+    let out = match out {
+        Some(out) => config.output_file(out)?,
+        None => config.default_output_file("keystore", &keypair.pubkey())?
+            .ok_or("Pass --out or --output-dir to choose where to write the keystore")?,
+    };
+
+    let passphrase = read_passphrase("New keystore passphrase: ")?;
+    if env::var("SOLANA_PASSPHRASE").is_err() && rpassword::prompt_password("Repeat the passphrase: ")? != passphrase {
+        return Err("Passphrases do not match".into());
+    }
+    if passphrase.is_empty() {
+        return Err("Passphrase must not be empty".into());
+    }
+
+    let keystore = encrypt_keypair(&keypair, &passphrase)?;
+    let mut file = create_secret_file(&out, force)?;
+    file.write_all(serde_json::to_string_pretty(&keystore)?.as_bytes())?;
+
+    config.output(
+        format!("🔐 Wrote the encrypted keypair {} to {}", keypair.pubkey(), out),
+        json!({ "pubkey": keypair.pubkey().to_string(), "keystore": out }),
+    );
+    Ok(())
+}
+
+fn load_keypair_from_env() -> Result<Keypair, CliError> {
+    // SECRET_KEY may also be set in the environment itself, so a missing .env is fine.
+    dotenv().ok();
+    let private_key = env::var("SECRET_KEY").map_err(|_| CliError::KeypairLoad(
+        "No keypair found, pass --keypair, --keystore or --wallet, or add SECRET_KEY to .env".to_string(),
+    ))?;
+    keypair_from_secret(&private_key, "SECRET_KEY from .env")
+}
+
+// Accept both the Solana CLI JSON byte array and the base58 string most wallets export.
+fn keypair_from_secret(secret: &str, source: &str) -> Result<Keypair, CliError> {
+    let as_array: Vec<u8> = match serde_json::from_str(secret.trim()) {
+        Ok(as_array) => as_array,
+        Err(_) => bs58::decode(secret.trim()).into_vec().map_err(|_| CliError::KeypairLoad(
+            format!("Failed to parse {}, expected a JSON array of 64 bytes or a base58 string", source),
+        ))?,
+    };
+    Keypair::from_bytes(&as_array)
+        .map_err(|e| CliError::KeypairLoad(format!("Failed to create Keypair from secret key: {}", e)))
+}
+
+// Named wallets and endpoints live in a JSON file like
+// {"wallets": {"main": {"path": "..."}, "cold": {"keystore": "..."}, "bot": {"secret": "..."}},
+//  "endpoints": {"local": "http://localhost:8899"}, "default_wallet": "main", "default_endpoint": "local",
+//  "mirrors": {"devnet": ["https://..."]}}
+// where mirrors are the extra endpoints --auto-rpc tries for each cluster.
+fn tool_config_path() -> Option<PathBuf> {
+    env::var(TOOL_CONFIG_ENV).ok().map(PathBuf::from).or_else(|| {
+        env::var("HOME").ok().map(|home| Path::new(&home).join(TOOL_CONFIG_PATH))
+    })
+}
+
+// A missing config file reads as an empty one.
+fn read_tool_config() -> Result<serde_json::Value, CliError> {
+    let Some(path) = tool_config_path().filter(|path| path.exists()) else {
+        return Ok(json!({}));
+    };
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&contents)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e).into())
+}
+
+enum Wallet {
+    File(String),
+    Keystore(String),
+    Secret(String),
+}
+
+fn resolve_wallet(tool_config: &serde_json::Value, name: &str) -> Result<Wallet, CliError> {
+    let wallet = &tool_config["wallets"][name];
+    if wallet.is_null() {
+        return Err(CliError::InvalidArgument(format!("Invalid wallet: no wallet named '{}' in the config file", name)));
+    }
+    match (wallet["path"].as_str(), wallet["keystore"].as_str(), wallet["secret"].as_str()) {
+        (Some(path), None, None) => Ok(Wallet::File(path.to_string())),
+        (None, Some(path), None) => Ok(Wallet::Keystore(path.to_string())),
+        (None, None, Some(secret)) => Ok(Wallet::Secret(secret.to_string())),
+        _ => Err(CliError::InvalidArgument(format!("Invalid wallet '{}': it needs exactly one of path, keystore or secret", name))),
+    }
+}
+
+fn resolve_endpoint(tool_config: &serde_json::Value, name: &str) -> Result<String, CliError> {
+    tool_config["endpoints"][name].as_str()
+        .map(str::to_string)
+        .ok_or_else(|| CliError::InvalidArgument(format!("Invalid endpoint: no endpoint named '{}' in the config file", name)))
+}
+
+// A keypair file signs right away, a bare pubkey can only sign the --offline file later.
+// Anything that isn't a pubkey has to be a readable keypair file.
+fn parse_fee_payer(value: &str) -> Result<(Pubkey, Option<Keypair>), CliError> {
+    if let Ok(pubkey) = Pubkey::from_str(value) {
+        return Ok((pubkey, None));
+    }
+    let keypair = load_keypair_from_file(value)
+        .map_err(|e| CliError::KeypairLoad(format!("--fee-payer {} is not a pubkey, and {}", value, e)))?;
+    Ok((keypair.pubkey(), Some(keypair)))
+}
+
+fn load_keypair_from_file(path: &str) -> Result<Keypair, CliError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| CliError::KeypairLoad(format!("Failed to read keypair file {}: {}", path, e)))?;
+    let as_array: Vec<u8> = serde_json::from_str(&contents)
+        .map_err(|e| CliError::KeypairLoad(format!("Failed to parse keypair file {}: {}", path, e)))?;
+    Keypair::from_bytes(&as_array)
+        .map_err(|e| CliError::KeypairLoad(format!("Failed to create Keypair from {}: {}", path, e)))
+}
+
+fn keypair_from_mnemonic(phrase: &str, passphrase: &str, derivation_path: &str) -> Result<Keypair, CliError> {
+    let mnemonic = Mnemonic::from_phrase(phrase.trim(), Language::English)
+        .map_err(|e| CliError::InvalidArgument(format!("Invalid seed phrase: {}", e)))?;
+    let derivation_path = DerivationPath::from_absolute_path_str(derivation_path)
+        .map_err(|e| CliError::InvalidArgument(format!("Invalid derivation path '{}': {}", derivation_path, e)))?;
+    let seed = Seed::new(&mnemonic, passphrase);
+    keypair_from_seed_and_derivation_path(seed.as_bytes(), Some(derivation_path))
+        .map_err(|e| format!("Failed to derive the keypair: {}", e).into())
+}
+
+fn recover_keypair(
+    config: &Config,
+    mnemonic: Option<&str>,
+    passphrase: &str,
+    derivation_path: &str,
+    secret_output: &SecretOutput,
+) -> Result<(), CliError> {
+    let mnemonic = match mnemonic {
+        Some(mnemonic) => mnemonic.to_string(),
+        None => {
+            dotenv().ok();
+            env::var("SOLANA_MNEMONIC").map_err(|_| "Pass --mnemonic or set SOLANA_MNEMONIC")?
+        }
+    };
+    let keypair = keypair_from_mnemonic(&mnemonic, passphrase, derivation_path)?;
+    let (human, mut json) = report_keypair(config, &keypair, secret_output)?;
+    json["derivation_path"] = json!(derivation_path);
+    config.output(format!("{}\n✅ Finished!", human), json);
+    Ok(())
+}
+
+// The --ledger device when one is selected, otherwise the first keypair source that is configured.
+fn load_signer(config: &Config) -> Result<Box<dyn Signer>, CliError> {
+    if let Some(derivation_path) = &config.ledger {
+        return load_ledger_signer(config, derivation_path);
+    }
+    Ok(Box::new(load_keypair_from_config(config)?))
+}
+
+fn load_keypair_from_config(config: &Config) -> Result<Keypair, CliError> {
+    match (&config.keypair_path, &config.keystore, &config.wallet_secret) {
+        (Some(path), _, _) => load_keypair_from_file(path),
+        (None, Some(path), _) => load_keypair_from_keystore(path).map_err(|e| CliError::KeypairLoad(e.to_string())),
+        (None, None, Some(secret)) => keypair_from_secret(secret, &format!("the secret of wallet '{}'", config.wallet.as_deref().unwrap_or_default())),
+        (None, None, None) => load_keypair_from_env(),
+    }
+}
+
+// Needs the `ledger` cargo feature for USB access.
+fn load_ledger_signer(config: &Config, derivation_path: &DerivationPath) -> Result<Box<dyn Signer>, CliError> {
+    let wallet_manager = maybe_wallet_manager()
+        .map_err(|e| match cfg!(feature = "ledger") {
+            true => format!("Failed to open the Ledger: {:?}", e),
+            false => "Ledger support is not built in, rebuild with `cargo build --features ledger`".to_string(),
+        })?
+        .ok_or("No Ledger found, connect it, unlock it and open the Solana app")?;
+    let signer = generate_remote_keypair(
+        Locator::new_from_path("usb://ledger")?,
+        derivation_path.clone(),
+        &wallet_manager,
+        false,
+        "ledger",
+    ).map_err(|e| format!("Failed to load the Ledger keypair: {}", e))?;
+    config.info(format!("🔐 Signing with the Ledger key {}, approve each transaction on the device", signer.pubkey()));
+    Ok(Box::new(signer))
+}
+
+fn load_keypair(config: &Config) -> Result<(), CliError> {
+    let keypair = load_signer(config)?;
+    let public_key = bs58::encode(keypair.pubkey()).into_string();
+    config.output(format!("Public key: {}", public_key), json!({ "pubkey": public_key }));
+    Ok(())
+}
+
+fn parse_pubkey(value: &str) -> Result<Pubkey, CliError> {
+    Pubkey::from_str(value).map_err(|e| CliError::InvalidPubkey { value: value.to_string(), reason: e.to_string() })
+}
+
+fn sign_message(config: &Config, message: &str) -> Result<(), CliError> {
+    let keypair = load_signer(config)?;
+    let signature = keypair.sign_message(message.as_bytes());
+    config.output(
+        format!("✍️ Signature by {}: {}", keypair.pubkey(), signature),
+        json!({ "pubkey": keypair.pubkey().to_string(), "signature": signature.to_string() }),
+    );
+    Ok(())
+}
+
+fn verify_message(config: &Config, pubkey: &str, message: &str, signature: &str) -> Result<(), CliError> {
+    let pubkey = parse_pubkey(pubkey)?;
+    let signature = Signature::from_str(signature)
+        .map_err(|e| CliError::InvalidArgument(format!("Invalid signature '{}': {}", signature, e)))?;
+    let valid = signature.verify(pubkey.as_ref(), message.as_bytes());
+    config.output(
+        if valid {
+            format!("✅ The signature is valid for {}", pubkey)
+        } else {
+            format!("❌ The signature is invalid for {}", pubkey)
+        },
+        json!({ "pubkey": pubkey.to_string(), "valid": valid }),
+    );
+    // Scripts checking a signature go by the exit code.
+    if !valid {
+        return Err(CliError::SignatureMismatch { pubkey });
+    }
+    Ok(())
+}
+
+fn resolve_rpc_url(rpc_url: Option<&str>, cluster: Option<&str>) -> Result<String, CliError> {
+    let cluster_url = cluster.map(|cluster| match cluster {
+        "testnet" => "https://api.testnet.solana.com",
+        "mainnet-beta" => "https://api.mainnet-beta.solana.com",
+        "localhost" => "http://localhost:8899",
+        _ => DEVNET_RPC_URL,
+    });
+
+    match (rpc_url, cluster_url) {
+        (Some(rpc_url), Some(cluster_url)) if rpc_url.trim_end_matches('/') != cluster_url => Err(format!(
+            "--rpc-url {} conflicts with --cluster {}, pass only one of them",
+            rpc_url,
+            cluster.unwrap_or_default()
+        ).into()),
+        (Some(rpc_url), _) => Ok(rpc_url.to_string()),
+        (None, Some(cluster_url)) => Ok(cluster_url.to_string()),
+        (None, None) => Ok(DEVNET_RPC_URL.to_string()),
+    }
+}
+
+// The canonical endpoint of the cluster, plus the mirrors the config file lists for it.
+fn auto_rpc_candidates<'a>(tool_config: &'a serde_json::Value, cluster_url: &'a str) -> Result<Vec<&'a str>, CliError> {
+    let cluster = cluster_name(cluster_url);
+    let mirrors = tool_config["mirrors"][cluster].as_array();
+    let candidates: Vec<&str> = std::iter::once(cluster_url)
+        .chain(mirrors.into_iter().flatten().filter_map(serde_json::Value::as_str))
+        .collect();
+    // With nothing to compare there is no choice to make, so don't pretend one was made.
+    if candidates.len() < 2 {
+        return Err(CliError::InvalidArgument(format!(
+            "--auto-rpc has only {} to measure, list more {} endpoints under \"mirrors\" in the config file",
+            cluster_url, cluster
+        )));
+    }
+    Ok(candidates)
+}
+
+// Times getSlot on every endpoint at once, leaving out the unhealthy or unreachable ones.
+fn fastest_endpoint(urls: &[&str]) -> Result<(String, Duration), CliError> {
+    let latencies: Vec<(String, Duration)> = thread::scope(|scope| {
+        let handles: Vec<_> = urls.iter().map(|url| scope.spawn(move || {
+            let connection = RpcClient::new_with_timeout(url.to_string(), Duration::from_secs(AUTO_RPC_TIMEOUT_SECS));
+            if let Err(e) = connection.get_health() {
+                log::info!("Skipping {}: {}", url, e);
+                return None;
+            }
+            let start_time = Instant::now();
+            connection.get_slot().ok()?;
+            Some((url.to_string(), start_time.elapsed()))
+        })).collect();
+        handles.into_iter().filter_map(|handle| handle.join().ok().flatten()).collect()
+    });
+    for (url, latency) in &latencies {
+        log::info!("{} answered getSlot in {} ms", url, latency.as_millis());
+    }
+    latencies.into_iter()
+        .min_by_key(|(_, latency)| *latency)
+        .ok_or_else(|| format!("None of {} is healthy, pass --rpc-url instead", urls.join(", ")).into())
+}
+
+fn read_state() -> Result<serde_json::Value, CliError> {
+    match fs::read_to_string(STATE_FILE) {
+        Ok(contents) => serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse {}: {}", STATE_FILE, e).into()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(serde_json::json!({})),
+        Err(e) => Err(format!("Failed to read {}: {}", STATE_FILE, e).into()),
+    }
+}
+
+fn save_state_value(key: &str, value: &str) -> Result<(), CliError> {
+    let mut state = read_state()?;
+    state[key] = serde_json::Value::String(value.to_string());
+    fs::write(STATE_FILE, serde_json::to_string_pretty(&state)?)?;
+    Ok(())
+}
+
+fn resolve_mint(mint: Option<&str>) -> Result<Pubkey, CliError> {
+    if let Some(mint) = mint {
+        return parse_pubkey(mint);
+    }
+    match read_state()?["mint"].as_str() {
+        Some(mint) => Ok(parse_pubkey(mint)?),
+        None => Err(format!(
+            "No mint given, pass --mint or run create-mint to save one in {}",
+            STATE_FILE
+        ).into()),
+    }
+}
+
+// Points explorer links at the cluster behind the RPC endpoint, or at the endpoint itself if it is custom.
+fn explorer_link(kind: &str, id: impl std::fmt::Display, rpc_url: &str) -> String {
+    let cluster_query = match rpc_url.trim_end_matches('/') {
+        "https://api.mainnet-beta.solana.com" => String::new(),
+        "https://api.testnet.solana.com" => "?cluster=testnet".to_string(),
+        DEVNET_RPC_URL => "?cluster=devnet".to_string(),
+        custom_url => {
+            let encoded_url: String = custom_url.bytes().map(|byte| match byte {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+                _ => format!("%{:02X}", byte),
+            }).collect();
+            format!("?cluster=custom&customUrl={}", encoded_url)
+        }
+    };
+    format!("https://explorer.solana.com/{}/{}{}", kind, id, cluster_query)
+}
+
+// Asks before moving real value on mainnet-beta, unless --yes was given or nothing will be sent.
+fn confirm(config: &Config, summary: &str) -> Result<(), CliError> {
+    if config.yes || !config.sends_transactions() || !is_mainnet(&config.rpc_url) {
+        return Ok(());
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(format!("{} on mainnet-beta needs confirmation, pass --yes to skip it", summary).into());
+    }
+
+    eprint!("⚠️ {} on mainnet-beta ({})? [y/N] ", summary, config.rpc_url);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        Ok(())
+    } else {
+        Err("Aborted, nothing was sent".into())
+    }
+}
+
+fn is_mainnet(rpc_url: &str) -> bool {
+    rpc_url.contains("mainnet")
+}
+
+// The transaction as --dump-tx shows it: the base64 wire format plus each instruction's
+// program, accounts and base58 data.
+fn describe_transaction(transaction: &Transaction) -> Result<serde_json::Value, CliError> {
+    let message = &transaction.message;
+    let instructions: Vec<_> = message.instructions.iter().map(|instruction| json!({
+        "program_id": message.account_keys[instruction.program_id_index as usize].to_string(),
+        "accounts": instruction.accounts.iter().map(|&index| json!({
+            "pubkey": message.account_keys[index as usize].to_string(),
+            "signer": message.is_signer(index as usize),
+            "writable": message.is_writable(index as usize),
+        })).collect::<Vec<_>>(),
+        "data": bs58::encode(&instruction.data).into_string(),
+    })).collect();
+    Ok(json!({
+        "base64": BASE64.encode(bincode::serialize(transaction)?),
+        "fee_payer": message.account_keys.first().map(Pubkey::to_string),
+        "recent_blockhash": message.recent_blockhash.to_string(),
+        "signatures": transaction.signatures.iter().map(Signature::to_string).collect::<Vec<_>>(),
+        "instructions": instructions,
+    }))
+}
+
+// Goes to stderr in --json mode like progress messages, and is printed even with --quiet.
+fn dump_transaction(config: &Config, transaction: &Transaction) -> Result<(), CliError> {
+    let description = describe_transaction(transaction)?;
+    if config.json {
+        eprintln!("{}", json!({ "transaction": description }));
+        return Ok(());
+    }
+
+    println!("🔍 Transaction: {}", description["base64"].as_str().unwrap_or_default());
+    println!("  Fee payer: {}", description["fee_payer"].as_str().unwrap_or_default());
+    println!("  Recent blockhash: {}", description["recent_blockhash"].as_str().unwrap_or_default());
+    for (index, instruction) in description["instructions"].as_array().into_iter().flatten().enumerate() {
+        println!("  Instruction {}: program {}", index, instruction["program_id"].as_str().unwrap_or_default());
+        for account in instruction["accounts"].as_array().into_iter().flatten() {
+            let flags = match (account["signer"].as_bool(), account["writable"].as_bool()) {
+                (Some(true), Some(true)) => " (signer, writable)",
+                (Some(true), _) => " (signer)",
+                (_, Some(true)) => " (writable)",
+                _ => "",
+            };
+            println!("    {}{}", account["pubkey"].as_str().unwrap_or_default(), flags);
+        }
+        println!("    data: {}", instruction["data"].as_str().unwrap_or_default());
+    }
+    Ok(())
+}
+
+// Prints the estimated fee of a signed transaction (and the transaction itself with --dump-tx),
+// simulates it with --simulate, and tells whether it should actually be sent.
+fn preflight(config: &Config, connection: &impl SolanaClient, transaction: &Transaction) -> Result<bool, CliError> {
+    if config.dump_tx {
+        dump_transaction(config, transaction)?;
+    }
+
+    if let Some(path) = &config.offline {
+        let path = &config.output_file(path)?;
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|e| format!("Failed to create {}: {}", path, e))?;
+        file.write_all(BASE64.encode(bincode::serialize(transaction)?).as_bytes())?;
+        let missing = missing_signers(transaction);
+        if missing.is_empty() {
+            config.output(
+                format!("✍️ Signed transaction written to {}, broadcast it with `submit --file {}`", path, path),
+                json!({ "offline": true, "file": path, "signature": transaction.signatures[0].to_string() }),
+            );
+        } else {
+            config.output(
+                format!(
+                    "✍️ Partially signed transaction written to {}, it still needs `add-signature --file {}` from: {}",
+                    path, path, missing.iter().map(Pubkey::to_string).collect::<Vec<_>>().join(", ")
+                ),
+                json!({ "offline": true, "file": path, "missing_signers": missing.iter().map(Pubkey::to_string).collect::<Vec<_>>() }),
+            );
+        }
+        return Ok(false);
+    }
+
+    let fee_in_lamports = connection.get_fee_for_message(&transaction.message)?;
+    log::debug!("Transaction has {} instruction(s) and {} signature(s)", transaction.message.instructions.len(), transaction.signatures.len());
+    config.info(format!("💵 Estimated fee: {} SOL", fee_in_lamports as f64 / LAMPORTS_PER_SOL as f64));
+    check_max_fee(config, fee_in_lamports)?;
+
+    if config.simulate {
+        let simulation = connection.simulate_transaction(transaction)?;
+        let logs = simulation.logs.unwrap_or_default();
+        let mut human = String::from("🧪 Simulation logs:");
+        for log in &logs {
+            human.push_str(&format!("\n  {}", log));
+        }
+        human.push_str(&format!("\n⚙️ Compute units consumed: {}", simulation.units_consumed.unwrap_or_default()));
+        human.push_str(&match &simulation.err {
+            Some(e) => format!("\n❌ The transaction would fail: {}", e),
+            None => "\n✅ The transaction would succeed, it was not sent".to_string(),
+        });
+        config.output(
+            human,
+            json!({
+                "simulated": true,
+                "fee_lamports": fee_in_lamports,
+                "err": simulation.err.map(|e| e.to_string()),
+                "logs": logs,
+                "units_consumed": simulation.units_consumed,
+            }),
+        );
+        return Ok(false);
+    }
+    if config.dry_run {
+        config.output(
+            "🧪 Dry run, the transaction was not sent",
+            json!({ "dry_run": true, "fee_lamports": fee_in_lamports }),
+        );
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+// --offline writes a single transaction file, so a command about to build several refuses it
+// before anything is signed.
+fn check_offline_transaction_count(config: &Config, count: usize) -> Result<(), CliError> {
+    if config.offline.is_some() && count > 1 {
+        return Err(CliError::InvalidArgument(format!(
+            "--offline writes a single transaction, but this needs {}; split the input or send it without --offline",
+            count
+        )));
+    }
+    Ok(())
+}
+
+// The RPC calls the token commands rely on, so they can also run against an in-process test validator.
+trait SolanaClient {
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account>;
+    fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64>;
+    fn get_latest_blockhash(&self) -> ClientResult<Hash>;
+    fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64>;
+    fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64>;
+    fn simulate_transaction(&self, transaction: &Transaction) -> ClientResult<RpcSimulateTransactionResult>;
+    fn send_transaction(&self, transaction: &Transaction) -> ClientResult<Signature>;
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature>;
+}
+
+impl SolanaClient for RpcClient {
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        RpcClient::get_account(self, pubkey)
+    }
+
+    fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        RpcClient::get_balance(self, pubkey)
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        RpcClient::get_latest_blockhash(self)
+    }
+
+    fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64> {
+        RpcClient::get_minimum_balance_for_rent_exemption(self, data_len)
+    }
+
+    fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64> {
+        RpcClient::get_fee_for_message(self, message)
+    }
+
+    fn simulate_transaction(&self, transaction: &Transaction) -> ClientResult<RpcSimulateTransactionResult> {
+        Ok(RpcClient::simulate_transaction(self, transaction)?.value)
+    }
+
+    fn send_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        RpcClient::send_transaction(self, transaction)
+    }
+
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        RpcClient::send_and_confirm_transaction(self, transaction)
+    }
+}
+
+// Prepends the nonce advance and the --compute-limit and --priority-fee compute budget instructions when they are set.
+fn new_transaction(config: &Config, instructions: &[Instruction], payer: &Pubkey) -> Transaction {
+    let mut all_instructions = vec![];
+    // A durable nonce transaction has to advance the nonce in its first instruction.
+    if let Some(nonce_account) = &config.nonce_account {
+        let nonce_authority = config.nonce_authority.as_ref().map_or(*payer, |authority| authority.pubkey());
+        all_instructions.push(system_instruction::advance_nonce_account(nonce_account, &nonce_authority));
+    }
+    if let Some(compute_limit) = config.compute_limit {
+        all_instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(compute_limit));
+    }
+    if let Some(priority_fee) = config.priority_fee {
+        all_instructions.push(ComputeBudgetInstruction::set_compute_unit_price(priority_fee));
+    }
+    all_instructions.extend_from_slice(instructions);
+    Transaction::new_with_payer(&all_instructions, Some(config.fee_payer.as_ref().unwrap_or(payer)))
+}
+
+// Signs with the given keypairs plus the --nonce-authority when it is a separate keypair.
+// A --fee-payer keypair signs too; given only its pubkey the transaction stays partially signed.
+fn sign_transaction(
+    config: &Config,
+    transaction: &mut Transaction,
+    signers: &[&dyn Signer],
+    blockhash: Hash,
+) -> Result<(), CliError> {
+    let mut all_signers = signers.to_vec();
+    if let (Some(_), Some(nonce_authority)) = (&config.nonce_account, &config.nonce_authority) {
+        if !signers.iter().any(|signer| signer.pubkey() == nonce_authority.pubkey()) {
+            all_signers.push(nonce_authority);
+        }
+    }
+    if let Some(fee_payer) = &config.fee_payer_keypair {
+        if !all_signers.iter().any(|signer| signer.pubkey() == fee_payer.pubkey()) {
+            all_signers.push(fee_payer);
+        }
+    }
+    if config.fee_payer.is_some() && config.fee_payer_keypair.is_none() {
+        transaction.try_partial_sign(&all_signers, blockhash)?;
+    } else {
+        transaction.try_sign(&all_signers, blockhash)?;
+    }
+    Ok(())
+}
+
+// Guards against a fat-fingered --priority-fee draining the wallet.
+fn check_max_fee(config: &Config, fee_in_lamports: u64) -> Result<(), CliError> {
+    if fee_in_lamports > config.max_fee {
+        return Err(format!(
+            "Estimated fee of {} SOL exceeds the allowed {} SOL, raise --max-fee to send it anyway",
+            fee_in_lamports as f64 / LAMPORTS_PER_SOL as f64,
+            config.max_fee as f64 / LAMPORTS_PER_SOL as f64,
+        ).into());
+    }
+    Ok(())
+}
+
+// Required signers whose signature is still missing.
+fn missing_signers(transaction: &Transaction) -> Vec<Pubkey> {
+    transaction.message.account_keys
+        .iter()
+        .zip(&transaction.signatures)
+        .filter(|(_, signature)| **signature == Signature::default())
+        .map(|(pubkey, _)| *pubkey)
+        .collect()
+}
+
+// Sends a transaction and waits for it to be confirmed, over a signature subscription with
+// --websocket and by polling the RPC otherwise.
+fn send_and_confirm(config: &Config, connection: &impl SolanaClient, transaction: &Transaction) -> Result<Signature, CliError> {
+    let signature = match subscribe_signature(config, &transaction.signatures[0]) {
+        Some(subscription) => {
+            let signature = with_retry(config.max_retries, "sendTransaction", || connection.send_transaction(transaction))?;
+            wait_for_signature(subscription, &signature, Duration::from_secs(WEBSOCKET_CONFIRM_TIMEOUT_SECS))?;
+            signature
+        }
+        None => with_retry(config.max_retries, "sendTransaction", || connection.send_and_confirm_transaction(transaction))?,
+    };
+    Ok(signature)
+}
+
+// Collects the instructions and signers of one transaction and runs the blockhash,
+// signing, preflight and send steps the commands share.
+struct TxBuilder<'a> {
+    config: &'a Config,
+    payer: &'a dyn Signer,
+    instructions: Vec<Instruction>,
+    signers: Vec<&'a dyn Signer>,
+}
+
+impl<'a> TxBuilder<'a> {
+    fn new(config: &'a Config, payer: &'a dyn Signer) -> Self {
+        TxBuilder { config, payer, instructions: vec![], signers: vec![payer] }
+    }
+
+    fn instruction(mut self, instruction: Instruction) -> Self {
+        self.instructions.push(instruction);
+        self
+    }
+
+    fn instructions(mut self, instructions: impl IntoIterator<Item = Instruction>) -> Self {
+        self.instructions.extend(instructions);
+        self
+    }
+
+    fn signer(mut self, signer: &'a dyn Signer) -> Self {
+        self.signers.push(signer);
+        self
+    }
+
+    fn build(&self, connection: &impl SolanaClient) -> Result<Transaction, CliError> {
+        let mut transaction = new_transaction(self.config, &self.instructions, &self.payer.pubkey());
+        let recent_blockhash = latest_blockhash(self.config, connection)?;
+        sign_transaction(self.config, &mut transaction, &self.signers, recent_blockhash)?;
+        Ok(transaction)
+    }
+
+    // Returns None when --dry-run, --simulate or --offline kept the transaction from being sent.
+    fn submit(&self, connection: &impl SolanaClient, transaction: &Transaction) -> Result<Option<Signature>, CliError> {
+        if !preflight(self.config, connection, transaction)? {
+            return Ok(None);
+        }
+        Ok(Some(send_and_confirm(self.config, connection, transaction)?))
+    }
+
+    fn send(&self, connection: &impl SolanaClient) -> Result<Option<Signature>, CliError> {
+        let transaction = self.build(connection)?;
+        self.submit(connection, &transaction)
+    }
+}
+
+fn latest_blockhash(config: &Config, connection: &impl SolanaClient) -> Result<Hash, CliError> {
+    match (config.blockhash, &config.nonce_account) {
+        (Some(blockhash), _) => Ok(blockhash),
+        (None, Some(nonce_account)) => nonce_blockhash(connection, nonce_account),
+        (None, None) => Ok(with_retry(config.max_retries, "getLatestBlockhash", || connection.get_latest_blockhash())?),
+    }
+}
+
+fn nonce_blockhash(connection: &impl SolanaClient, nonce_account: &Pubkey) -> Result<Hash, CliError> {
+    let account = connection.get_account(nonce_account)
+        .map_err(|e| format!("Failed to fetch nonce account {}: {}", nonce_account, e))?;
+    let versions: nonce::state::Versions = bincode::deserialize(&account.data)
+        .ok()
+        .filter(|_| account.owner == system_program::id())
+        .ok_or_else(|| format!("{} is not a nonce account", nonce_account))?;
+    match versions.state() {
+        nonce::State::Initialized(data) => Ok(data.blockhash()),
+        nonce::State::Uninitialized => Err(format!("Nonce account {} is not initialized", nonce_account).into()),
+    }
+}
+
+// Timeouts, dropped connections, rate limiting and server side failures are worth another try.
+fn is_transient(error: &ClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(e) => {
+            e.is_timeout()
+                || e.is_connect()
+                || e.status().is_some_and(|status| status.as_u16() == 429 || status.is_server_error())
+        }
+        _ => false,
+    }
+}
+
+fn with_retry<T>(max_retries: u32, method: &str, mut op: impl FnMut() -> ClientResult<T>) -> ClientResult<T> {
+    let mut delay = Duration::from_millis(500);
+    let mut attempt = 0;
+    loop {
+        let start_time = Instant::now();
+        let result = op();
+        log::debug!("{} took {:?}", method, start_time.elapsed());
+        match result {
+            Err(e) if attempt < max_retries && is_transient(&e) => {
+                attempt += 1;
+                log::warn!("{} failed ({}), retrying in {:?} ({}/{})", method, e, delay, attempt, max_retries);
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn create_connection(config: &Config) -> RpcClient {
+    log::info!("Connecting to {} with {:?} commitment", config.rpc_url, config.commitment.commitment);
+    RpcClient::new_with_commitment(
+        config.rpc_url.clone(),
+        config.commitment,
+    )
+}
+
+async fn check_balance(
+    config: &Config,
+    address: Option<&str>,
+    airdrop: bool,
+    airdrop_amount: f64,
+    min_balance: f64,
+    airdrop_timeout_secs: u64,
+    tokens: bool,
+) -> Result<(), CliError> {
+    // Only top up the wallet we actually sign for, never an arbitrary --address.
+    let (public_key, is_own_wallet) = match address {
+        Some(address) => (parse_pubkey(address)?, false),
+        None => (load_signer(config)?.pubkey(), true),
+    };
+
+    let connection = create_connection(config);
+    config.info(format!("⚡️ Connected to {}", config.rpc_url));
+
+    if airdrop && is_own_wallet {
+        if let Err(e) = airdrop_if_required(
+            config,
+            &connection,
+            &public_key,
+            airdrop_amount,
+            min_balance,
+            airdrop_timeout_secs,
+        ).await {
+            // A warning rather than progress, so --quiet doesn't hide it.
+            log::warn!("Airdrop failed due to: {}", e);
+        }
+    }
+    
+    let balance_in_lamports = with_retry(config.max_retries, "getBalance", || connection.get_balance(&public_key))?;
+    let balance_in_sol = balance_in_lamports as f64 / LAMPORTS_PER_SOL as f64;
+    let mut human = format!("💰 The balance for the wallet at address {} is: {} SOL", public_key, balance_in_sol);
+    let mut json = json!({ "pubkey": public_key.to_string(), "lamports": balance_in_lamports, "sol": balance_in_sol });
+
+    if tokens {
+        let token_balances = fetch_token_balances(&connection, &public_key, &config.metadata_program_id)?;
+        if token_balances.is_empty() {
+            human.push_str("\n🪙 No token accounts found");
+        }
+        for token_balance in &token_balances {
+            human.push_str(&format!(
+                "\n🪙 {} {} (mint {})",
+                token_balance["amount"].as_str().unwrap_or_default(),
+                token_balance["symbol"].as_str().unwrap_or_default(),
+                token_balance["mint"].as_str().unwrap_or_default(),
+            ));
+        }
+        json["tokens"] = json!(token_balances);
+    }
+
+    config.output(human, json);
+
+    Ok(())
+}
+
+fn read_address_file(path: &str) -> Result<Vec<Pubkey>, CliError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let addresses = contents.lines().enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(index, line)| parse_pubkey(line.trim())
+            .map_err(|e| format!("Line {} of {}: {}", index + 1, path, e).into()))
+        .collect::<Result<Vec<_>, CliError>>()?;
+    if addresses.is_empty() {
+        return Err(format!("{} lists no addresses", path).into());
+    }
+    Ok(addresses)
+}
+
+// Fetches the accounts in batches of getMultipleAccounts, a few batches at a time
+// so the public RPC doesn't rate-limit us.
+async fn check_balances(config: &Config, path: &str) -> Result<(), CliError> {
+    let addresses = read_address_file(path)?;
+
+    let connection = Arc::new(create_connection(config));
+    config.info(format!("⚡️ Connected to {}", config.rpc_url));
+
+    let semaphore = Arc::new(Semaphore::new(MAX_CONCURRENT_RPC_REQUESTS));
+    let mut tasks = vec![];
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let permit = semaphore.clone().acquire_owned().await?;
+        let connection = connection.clone();
+        let chunk = chunk.to_vec();
+        let max_retries = config.max_retries;
+        tasks.push(tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            with_retry(max_retries, "getMultipleAccounts", || connection.get_multiple_accounts(&chunk))
+        }));
+    }
+
+    let mut balances = Vec::with_capacity(addresses.len());
+    for task in tasks {
+        let accounts = task.await??;
+        balances.extend(accounts.into_iter().map(|account| account.map_or(0, |account| account.lamports)));
+    }
+
+    let mut human = vec![];
+    let mut json = vec![];
+    for (address, balance_in_lamports) in addresses.iter().zip(balances) {
+        let balance_in_sol = balance_in_lamports as f64 / LAMPORTS_PER_SOL as f64;
+        human.push(format!("{:<44}  {} SOL", address, balance_in_sol));
+        json.push(json!({ "pubkey": address.to_string(), "lamports": balance_in_lamports, "sol": balance_in_sol }));
+    }
+    config.output(human.join("\n"), json!(json));
+
+    Ok(())
+}
+
+fn fetch_token_balances(
+    connection: &RpcClient,
+    owner: &Pubkey,
+    metadata_program_id: &Pubkey,
+) -> Result<Vec<serde_json::Value>, CliError> {
+    let mut token_balances = Vec::new();
+
+    for token_program_id in [spl_token::id(), spl_token_2022::id()] {
+        let token_accounts = connection.get_token_accounts_by_owner(
+            owner,
+            TokenAccountsFilter::ProgramId(token_program_id),
+        )?;
+
+        for token_account in token_accounts {
+            let UiAccountData::Json(parsed_account) = token_account.account.data else {
+                continue;
+            };
+            let info = &parsed_account.parsed["info"];
+            let mint = info["mint"].as_str().unwrap_or_default().to_string();
+            let symbol = parse_pubkey(&mint)
+                .ok()
+                .and_then(|mint| connection.get_account(&find_metadata_pda(metadata_program_id, &mint)).ok())
+                .and_then(|account| Metadata::from_bytes(&account.data).ok())
+                .map(|metadata| metadata.symbol.trim_end_matches('\0').to_string());
+
+            token_balances.push(json!({
+                "token_account": token_account.pubkey,
+                "mint": mint,
+                "amount": info["tokenAmount"]["uiAmountString"],
+                "symbol": symbol,
+            }));
+        }
+    }
+
+    Ok(token_balances)
+}
+
+fn signature_status_label(status: Option<&TransactionStatus>) -> String {
+    match status {
+        None => "not found".to_string(),
+        Some(status) if status.err.is_some() => "failed".to_string(),
+        Some(status) => status.confirmation_status.as_ref()
+            .map_or("processed".to_string(), |confirmation_status| format!("{:?}", confirmation_status).to_lowercase()),
+    }
+}
+
+// Looks signatures up in batches of the most getSignatureStatuses accepts, searching the
+// transaction history so signatures older than the status cache are still found.
+fn fetch_signature_statuses(
+    config: &Config,
+    connection: &RpcClient,
+    signatures: &[Signature],
+) -> Result<Vec<Option<TransactionStatus>>, CliError> {
+    let mut statuses = Vec::with_capacity(signatures.len());
+    for chunk in signatures.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS) {
+        let response = with_retry(config.max_retries, "getSignatureStatuses", || {
+            connection.get_signature_statuses_with_history(chunk)
+        })?;
+        statuses.extend(response.value);
+    }
+    Ok(statuses)
+}
+
+fn confirm_signatures(config: &Config, signatures: &[&str]) -> Result<(), CliError> {
+    let mut lines = vec![];
+    if signatures.is_empty() {
+        for line in std::io::stdin().lines() {
+            lines.push(line?);
+        }
+    }
+    let signatures = signatures.iter()
+        .copied()
+        .chain(lines.iter().map(|line| line.trim()).filter(|line| !line.is_empty()))
+        .map(|signature| Signature::from_str(signature).map_err(|e| CliError::InvalidArgument(format!("Invalid signature {}: {}", signature, e))))
+        .collect::<Result<Vec<_>, _>>()?;
+    if signatures.is_empty() {
+        return Err(CliError::InvalidArgument("Invalid input: pass signatures as arguments or one per line on stdin".to_string()));
+    }
+
+    let connection = create_connection(config);
+    config.info(format!("⚡️ Connected to {}", config.rpc_url));
+    let statuses = fetch_signature_statuses(config, &connection, &signatures)?;
+
+    let mut human = vec![];
+    let mut json = vec![];
+    for (signature, status) in signatures.iter().zip(&statuses) {
+        let label = signature_status_label(status.as_ref());
+        let mut line = format!("{:<88}  {}", signature, label);
+        if let Some(status) = status {
+            line.push_str(&format!(" (slot {})", status.slot));
+            if let Some(err) = &status.err {
+                line.push_str(&format!(" ❌ {}", err));
+            }
+        }
+        human.push(line);
+        json.push(json!({
+            "signature": signature.to_string(),
+            "status": label,
+            "slot": status.as_ref().map(|status| status.slot),
+            "err": status.as_ref().and_then(|status| status.err.as_ref()).map(|err| err.to_string()),
+        }));
+    }
+    config.output(human.join("\n"), json!(json));
+
+    Ok(())
+}
+
+async fn airdrop_if_required(
+    config: &Config,
+    connection: &RpcClient,
+    public_key: &Pubkey,
+    airdrop_amount: f64,
+    min_balance: f64,
+    timeout_secs: u64,
+) -> Result<(), CliError> {
+    if is_mainnet(&connection.url()) {
+        config.info("🚫 Airdrops are not available on mainnet-beta, skipping the airdrop");
+        return Ok(());
+    }
+
+    let current_balance = with_retry(config.max_retries, "getBalance", || connection.get_balance(public_key))?;
+    if current_balance < (min_balance * LAMPORTS_PER_SOL as f64) as u64 {
+        config.info("Requesting airdrop...");
+
+        let signature = connection
+            .request_airdrop(public_key, (airdrop_amount * LAMPORTS_PER_SOL as f64) as u64)?;
+        confirm_signature(config, connection, &signature, timeout_secs).await?;
+
+        config.info("Airdrop complete");
+    } else {
+        config.info("No airdrop required");
+    }
+    Ok(())
+}
+
+// The WebSocket endpoint of a validator listens one port above its HTTP RPC port.
+fn websocket_url(rpc_url: &str) -> String {
+    rpc_url
+        .replacen("https://", "wss://", 1)
+        .replacen("http://", "ws://", 1)
+        .replacen(":8899", ":8900", 1)
+}
+
+// Subscribes with --websocket, falling back to polling (None) when the endpoint can't be reached.
+fn subscribe_signature(config: &Config, signature: &Signature) -> Option<SignatureSubscription> {
+    if !config.websocket {
+        return None;
+    }
+    let url = websocket_url(&config.rpc_url);
+    let subscribe_config = RpcSignatureSubscribeConfig {
+        commitment: Some(config.commitment),
+        enable_received_notification: Some(false),
+    };
+    match PubsubClient::signature_subscribe(&url, signature, Some(subscribe_config)) {
+        Ok(subscription) => Some(subscription),
+        Err(e) => {
+            log::warn!("WebSocket {} is unreachable ({}), polling for confirmation instead", url, e);
+            None
+        }
+    }
+}
+
+fn wait_for_signature(
+    subscription: SignatureSubscription,
+    signature: &Signature,
+    timeout: Duration,
+) -> Result<(), CliError> {
+    let (mut client, receiver) = subscription;
+    let _interruptible = Interruptible::start();
+    // Wait in short slices so Ctrl-C is noticed.
+    let deadline = Instant::now() + timeout;
+    let notification = loop {
+        let slice = deadline.saturating_duration_since(Instant::now()).min(Duration::from_millis(500));
+        match receiver.recv_timeout(slice) {
+            Err(e) if e.is_timeout() && !interrupted() && Instant::now() < deadline => continue,
+            notification => break notification,
+        }
+    };
+    if let Err(e) = client.shutdown() {
+        log::debug!("Failed to close the signature subscription: {:?}", e);
+    }
+    match notification.map(|response| response.value) {
+        Ok(RpcSignatureResult::ProcessedSignature(ProcessedSignatureResult { err: None })) => Ok(()),
+        Ok(RpcSignatureResult::ProcessedSignature(ProcessedSignatureResult { err: Some(e) })) => {
+            Err(CliError::TransactionFailed(format!("Transaction {} failed: {}", signature, e)))
+        }
+        Ok(RpcSignatureResult::ReceivedSignature(_)) => {
+            Err(CliError::TransactionFailed(format!("Unexpected notification for {}", signature)))
+        }
+        Err(_) if interrupted() => Err(CliError::Interrupted(format!("Stopped waiting for {} to be confirmed", signature))),
+        Err(_) => Err(CliError::Timeout { signature: *signature, seconds: timeout.as_secs() }),
+    }
+}
+
+async fn confirm_signature(
+    config: &Config,
+    connection: &RpcClient,
+    signature: &Signature,
+    timeout_secs: u64,
+) -> Result<(), CliError> {
+    if let Some(subscription) = subscribe_signature(config, signature) {
+        return tokio::task::block_in_place(|| wait_for_signature(subscription, signature, Duration::from_secs(timeout_secs)));
+    }
+
+    let _interruptible = Interruptible::start();
+    let deadline = Instant::now() + Duration::from_secs(timeout_secs);
+    loop {
+        let confirmed = connection.confirm_transaction_with_commitment(signature, config.commitment)?;
+        if confirmed.value {
+            return Ok(());
+        }
+        if interrupted() {
+            return Err(CliError::Interrupted(format!("Stopped waiting for {} to be confirmed", signature)));
+        }
+        if Instant::now() >= deadline {
+            return Err(CliError::Timeout { signature: *signature, seconds: timeout_secs });
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+}
+
+async fn request_airdrop(
+    config: &Config,
+    address: Option<&str>,
+    amount_in_lamports: u64,
+    timeout_secs: u64,
+) -> Result<Signature, CliError> {
+    if amount_in_lamports == 0 {
+        return Err(CliError::InvalidArgument("Amount must be greater than zero".to_string()));
+    }
+    let amount_sol = amount_in_lamports as f64 / LAMPORTS_PER_SOL as f64;
+    if is_mainnet(&config.rpc_url) {
+        return Err("Airdrops are not available on mainnet-beta".into());
+    }
+    let public_key = match address {
+        Some(address) => parse_pubkey(address)?,
+        None => load_signer(config)?.pubkey(),
+    };
+
+    let connection = create_connection(config);
+    config.info(format!("🪂 Requesting an airdrop of {} SOL to {}...", amount_sol, public_key));
+
+    let signature = connection.request_airdrop(&public_key, amount_in_lamports)?;
+    confirm_signature(config, &connection, &signature, timeout_secs).await?;
+
+    let balance_in_lamports = with_retry(config.max_retries, "getBalance", || connection.get_balance(&public_key))?;
+    let balance_in_sol = balance_in_lamports as f64 / LAMPORTS_PER_SOL as f64;
+
+    config.output(
+        format!("✅ Airdrop confirmed, signature: {}\n💰 The balance of {} is now: {} SOL", signature, public_key, balance_in_sol),
+        json!({
+            "signature": signature.to_string(),
+            "pubkey": public_key.to_string(),
+            "lamports": balance_in_lamports,
+            "sol": balance_in_sol,
+        }),
+    );
+
+    Ok(signature)
+}
+
+fn check_rent(config: &Config, address: &Pubkey) -> Result<(), CliError> {
+    let connection = create_connection(config);
+    let (lamports, rent_exempt_minimum) = rent_exemption(&connection, address)?;
+
+    let surplus = lamports as i64 - rent_exempt_minimum as i64;
+    let human = if surplus >= 0 {
+        format!(
+            "✅ {} is rent exempt, it holds {} SOL more than the minimum of {} SOL",
+            address,
+            surplus as f64 / LAMPORTS_PER_SOL as f64,
+            rent_exempt_minimum as f64 / LAMPORTS_PER_SOL as f64
+        )
+    } else {
+        format!(
+            "⚠️ {} is not rent exempt, it needs {} SOL more to reach the minimum of {} SOL",
+            address,
+            -surplus as f64 / LAMPORTS_PER_SOL as f64,
+            rent_exempt_minimum as f64 / LAMPORTS_PER_SOL as f64
+        )
+    };
+
+    config.output(
+        human,
+        json!({
+            "pubkey": address.to_string(),
+            "lamports": lamports,
+            "rent_exempt_minimum": rent_exempt_minimum,
+            "rent_exempt": surplus >= 0,
+        }),
+    );
+
+    Ok(())
+}
+
+fn rent_exemption_for_size(config: &Config, size: usize) -> Result<(), CliError> {
+    let connection = create_connection(config);
+    let rent_exempt_minimum = with_retry(config.max_retries, "getMinimumBalanceForRentExemption", || {
+        connection.get_minimum_balance_for_rent_exemption(size)
+    })?;
+    let sol = rent_exempt_minimum as f64 / LAMPORTS_PER_SOL as f64;
+
+    config.output(
+        format!("🏠 An account of {} bytes needs {} lamports ({} SOL) to be rent exempt", size, rent_exempt_minimum, sol),
+        json!({ "size": size, "lamports": rent_exempt_minimum, "sol": sol }),
+    );
+    Ok(())
+}
+
+// Returns the lamports of an account and the minimum it needs to be rent exempt at its size.
+fn rent_exemption(connection: &impl SolanaClient, address: &Pubkey) -> Result<(u64, u64), CliError> {
+    let account = connection.get_account(address)
+        .map_err(|e| format!("Failed to fetch account {}: {}", address, e))?;
+    let rent_exempt_minimum = connection.get_minimum_balance_for_rent_exemption(account.data.len())?;
+    Ok((account.lamports, rent_exempt_minimum))
+}
+
+fn show_history(
+    config: &Config,
+    address: Option<&str>,
+    limit: usize,
+    details: bool,
+) -> Result<(), CliError> {
+    let public_key = match address {
+        Some(address) => parse_pubkey(address)?,
+        None => load_signer(config)?.pubkey(),
+    };
+
+    let connection = create_connection(config);
+    // Transaction history is not available at processed commitment.
+    let commitment = if config.commitment.is_at_least_confirmed() {
+        config.commitment
+    } else {
+        CommitmentConfig::confirmed()
+    };
+
+    let statuses = connection.get_signatures_for_address_with_config(
+        &public_key,
+        GetConfirmedSignaturesForAddress2Config {
+            limit: Some(limit),
+            commitment: Some(commitment),
+            ..GetConfirmedSignaturesForAddress2Config::default()
+        },
+    )?;
+
+    let mut human = format!("📜 Recent transactions of {}:", public_key);
+    if statuses.is_empty() {
+        human.push_str("\n  none");
+    }
+    let mut json = vec![];
+    for status in &statuses {
+        let confirmation_status = status.confirmation_status.as_ref()
+            .map_or("unknown".to_string(), |confirmation_status| format!("{:?}", confirmation_status).to_lowercase());
+        human.push_str(&format!("\n  {} slot {} {}", status.signature, status.slot, confirmation_status));
+        if let Some(err) = &status.err {
+            human.push_str(&format!(" ❌ {}", err));
+        }
+        if let Some(memo) = &status.memo {
+            human.push_str(&format!(" 📝 {}", memo));
+        }
+        let mut entry = json!({
+            "signature": status.signature,
+            "slot": status.slot,
+            "confirmation_status": confirmation_status,
+            "err": status.err.as_ref().map(|err| err.to_string()),
+            "memo": status.memo,
+            "block_time": status.block_time,
+        });
+
+        if details {
+            let transaction = connection.get_transaction_with_config(
+                &Signature::from_str(&status.signature)?,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(commitment),
+                    max_supported_transaction_version: Some(0),
+                },
+            )?;
+            if let Some(meta) = &transaction.transaction.meta {
+                // Only the static account keys are decoded, so lookup table accounts are never matched.
+                let balance_change = transaction.transaction.transaction.decode()
+                    .and_then(|decoded| decoded.message.static_account_keys().iter().position(|key| *key == public_key))
+                    .map(|index| meta.post_balances[index] as i64 - meta.pre_balances[index] as i64);
+                human.push_str(&format!("\n    fee {} SOL", meta.fee as f64 / LAMPORTS_PER_SOL as f64));
+                if let Some(balance_change) = balance_change {
+                    human.push_str(&format!(", balance change {:+} SOL", balance_change as f64 / LAMPORTS_PER_SOL as f64));
+                }
+                entry["fee_lamports"] = json!(meta.fee);
+                entry["balance_change_lamports"] = json!(balance_change);
+            }
+        }
+        json.push(entry);
+    }
+
+    config.output(human, json!({ "pubkey": public_key.to_string(), "transactions": json }));
+
+    Ok(())
+}
+
+// Reports what a confirmed transaction actually cost, to help calibrate --compute-limit and --priority-fee.
+fn report_transaction_cost(config: &Config, signature: Option<Signature>) -> Result<(), CliError> {
+    let Some(signature) = signature else {
+        return Ok(());
+    };
+
+    let connection = create_connection(config);
+    let commitment = if config.commitment.is_at_least_confirmed() {
+        config.commitment
+    } else {
+        CommitmentConfig::confirmed()
+    };
+    let transaction_config = RpcTransactionConfig {
+        encoding: Some(UiTransactionEncoding::Base64),
+        commitment: Some(commitment),
+        max_supported_transaction_version: Some(0),
+    };
+
+    let mut attempt = 1;
+    let transaction = loop {
+        match connection.get_transaction_with_config(&signature, transaction_config) {
+            Ok(transaction) => break transaction,
+            Err(e) if attempt < REPORT_FETCH_ATTEMPTS => {
+                log::debug!("getTransaction failed ({}), retrying ({}/{})", e, attempt, REPORT_FETCH_ATTEMPTS);
+                attempt += 1;
+                thread::sleep(Duration::from_secs(1));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+    let meta = transaction.transaction.meta
+        .ok_or_else(|| format!("The RPC returned no status metadata for {}", signature))?;
+    let compute_units_consumed = Option::<u64>::from(meta.compute_units_consumed);
+
+    // The command already printed its result, so with --json the report goes to stderr to keep
+    // stdout a single JSON document.
+    let human = format!(
+        "📊 Cost of {}:\n  Compute units consumed: {}{}\n  Fee paid: {} SOL{}",
+        signature,
+        compute_units_consumed.map_or("unknown".to_string(), |units| units.to_string()),
+        config.compute_limit.map_or(String::new(), |limit| format!(" of the {} requested", limit)),
+        meta.fee as f64 / LAMPORTS_PER_SOL as f64,
+        config.priority_fee.map_or(String::new(), |price| format!(" (priority fee {} micro-lamports per unit)", price)),
+    );
+    if config.json {
+        eprintln!("{}", json!({
+            "report": {
+                "signature": signature.to_string(),
+                "slot": transaction.slot,
+                "compute_units_consumed": compute_units_consumed,
+                "compute_limit": config.compute_limit,
+                "fee_lamports": meta.fee,
+                "priority_fee": config.priority_fee,
+            },
+        }));
+    } else {
+        config.info(human);
+    }
+    Ok(())
+}
+
+fn validate_base58(value: &str) -> Result<(), CliError> {
+    if let Some(invalid) = value.chars().find(|c| !BASE58_ALPHABET.contains(*c)) {
+        return Err(CliError::InvalidArgument(format!(
+            "'{}' contains '{}', which is not a base58 character (0, O, I and l are never used)",
+            value, invalid
+        )));
+    }
+    Ok(())
+}
+
+// Runs the search until its (short) deadline only to measure the attempts per second, then
+// projects how long the expected number of attempts takes at that rate.
+fn estimate_vanity_search(config: &Config, search: &VanitySearch, timeout_minutes: u64) -> Result<(), CliError> {
+    config.info(format!("⏱️ Measuring the keypair generation rate with {} thread(s)...", search.threads));
+    let start_time = Instant::now();
+    search.run();
+    let rate = search.attempts() as f64 / start_time.elapsed().as_secs_f64();
+    let expected_attempts = search.expected_attempts();
+    let expected_secs = expected_attempts / rate;
+
+    let mut human = format!(
+        "🔢 A public key {} takes about {:.0} attempts on average\n⚡ {:.0} attempts/s, so expect {}",
+        search.describe(),
+        expected_attempts,
+        rate,
+        format_duration(expected_secs)
+    );
+    if expected_secs > (timeout_minutes * 60) as f64 {
+        human.push_str(&format!("\n⚠️ That is longer than the {} minute timeout, raise --timeout-minutes", timeout_minutes));
+    }
+    config.output(
+        human,
+        json!({
+            "expected_attempts": expected_attempts,
+            "attempts_per_sec": rate,
+            "expected_secs": expected_secs,
+            "threads": search.threads,
+        }),
+    );
+    Ok(())
+}
+
+fn format_duration(secs: f64) -> String {
+    const UNITS: [(&str, f64); 5] = [
+        ("years", 365.0 * 86_400.0),
+        ("days", 86_400.0),
+        ("hours", 3_600.0),
+        ("minutes", 60.0),
+        ("seconds", 1.0),
+    ];
+    let (unit, length) = UNITS.iter().find(|(_, length)| secs >= *length).unwrap_or(&UNITS[4]);
+    format!("{:.1} {}", secs / length, unit)
+}
+
+fn expected_attempts(pattern: &str, ignore_case: bool) -> f64 {
+    pattern.chars().map(|c| {
+        let matching = if ignore_case {
+            BASE58_ALPHABET.chars().filter(|a| a.eq_ignore_ascii_case(&c)).count()
+        } else {
+            1
+        };
+        58.0 / matching as f64
+    }).product()
+}
+
+fn validate_vanity_pattern(pattern: &str, ignore_case: bool) -> Result<(), CliError> {
+    if ignore_case {
+        // Some letters only exist in one case in base58, so validate the casing the user will match against.
+        let has_match = |c: char| BASE58_ALPHABET.chars().any(|a| a.eq_ignore_ascii_case(&c));
+        if let Some(invalid) = pattern.chars().find(|c| !has_match(*c)) {
+            return Err(CliError::InvalidArgument(format!("'{}' contains '{}', which matches no base58 character in any case", pattern, invalid)));
+        }
+        Ok(())
+    } else {
+        validate_base58(pattern)
+    }
+}
+
+fn search_vanity_keypair(
+    config: &Config,
+    search: &VanitySearch,
+    max_minutes: u64,
+    secret_output: &SecretOutput<'_>,
+) -> Result<(), CliError> {
+    if let Some(path) = secret_output.out
+        .map(|path| config.output_file(path))
+        .transpose()?
+        .filter(|path| !secret_output.force && Path::new(path).exists())
+    {
+        return Err(format!("{} already exists, pass --force to overwrite it", path).into());
+    }
+
+    config.info(format!(
+        "⚠️ Expect about {:.0} attempts on average to find a public key {}",
+        search.expected_attempts(),
+        search.describe()
+    ));
+    config.info(format!("🔍 Searching with {} thread(s)...", search.threads));
+
+    let start_time = Instant::now();
+    let keypair = run_vanity_search(search);
+    let attempts = search.attempts();
+    match keypair {
+        Some(keypair) => {
+            let elapsed_time = start_time.elapsed();
+            let (human, mut json) = report_keypair(config, &keypair, secret_output)?;
+            json["elapsed_secs"] = json!(elapsed_time.as_secs_f64());
+            json["attempts"] = json!(attempts);
+            config.output(
+                format!(
+                    "⌛ Found matching keypair after {} attempts in {} second(s) or {:.2} minute(s)!\n{}\n✅ Finished!",
+                    attempts,
+                    elapsed_time.as_secs(),
+                    elapsed_time.as_secs_f64() / 60.0,
+                    human
+                ),
+                json,
+            );
+        }
+        None if interrupted() => {
+            return Err(CliError::Interrupted(format!(
+                "Interrupted after {} attempts in {} second(s), no public key {} was found",
+                attempts,
+                start_time.elapsed().as_secs(),
+                search.describe()
+            )));
+        }
+        None => {
+            config.output(
+                format!(
+                    "⏰ Time out! The public key {} was not found within {} minutes ({} attempts).",
+                    search.describe(),
+                    max_minutes,
+                    attempts
+                ),
+                json!({ "pubkey": null, "attempts": attempts }),
+            );
+        }
+    }
+    Ok(())
+}
+
+// Character classes for --prefix-class, matched against the base58 alphabet.
+type CharClass = fn(&char) -> bool;
+const VANITY_CLASSES: [(&str, CharClass); 4] = [
+    ("digits", char::is_ascii_digit),
+    ("upper", char::is_ascii_uppercase),
+    ("lower", char::is_ascii_lowercase),
+    ("letters", char::is_ascii_alphabetic),
+];
+
+/// Searches for a keypair whose base58 public key starts with `prefix` and ends with `suffix`.
+/// An empty pattern matches anything. Encoded keys are 43 or 44 characters long, and the suffix
+/// is checked against the end of the string whatever its length. `prefix_any` and `prefix_class`
+/// swap the literal prefix for a looser predicate.
+struct VanitySearch {
+    prefix_matches: Box<dyn Fn(&str) -> bool + Send + Sync>,
+    prefix_attempts: f64,
+    prefix_description: String,
+    suffix: String,
+    ignore_case: bool,
+    threads: usize,
+    deadline: Instant,
+    attempts: AtomicU64,
+}
+
+impl VanitySearch {
+    fn new(
+        prefix: &str,
+        suffix: &str,
+        ignore_case: bool,
+        threads: usize,
+        timeout: Duration,
+    ) -> Result<Self, CliError> {
+        validate_vanity_pattern(prefix, ignore_case)?;
+        validate_vanity_pattern(suffix, ignore_case)?;
+        let normalize = |pattern: &str| if ignore_case { pattern.to_lowercase() } else { pattern.to_string() };
+        let literal_prefix = normalize(prefix);
+        Ok(VanitySearch {
+            prefix_attempts: expected_attempts(&literal_prefix, ignore_case),
+            prefix_description: format!("'{}'", prefix),
+            prefix_matches: Box::new(move |public_key_base58| public_key_base58.starts_with(&literal_prefix)),
+            suffix: normalize(suffix),
+            ignore_case,
+            threads: threads.max(1),
+            deadline: Instant::now() + timeout,
+            attempts: AtomicU64::new(0),
+        })
+    }
+
+    // Matches a public key starting with any of `prefixes`.
+    fn prefix_any(mut self, prefixes: &[&str]) -> Result<Self, CliError> {
+        if prefixes.iter().any(|prefix| prefix.is_empty()) {
+            return Err(CliError::InvalidArgument("--prefix-any needs non-empty prefixes".to_string()));
+        }
+        for prefix in prefixes {
+            validate_vanity_pattern(prefix, self.ignore_case)?;
+        }
+        let prefixes: Vec<String> = prefixes.iter()
+            .map(|prefix| if self.ignore_case { prefix.to_lowercase() } else { prefix.to_string() })
+            .collect();
+        // The odds of the alternatives add up, overlapping ones make this a slight overestimate.
+        self.prefix_attempts = 1.0 / prefixes.iter()
+            .map(|prefix| 1.0 / expected_attempts(prefix, self.ignore_case))
+            .sum::<f64>();
+        self.prefix_description = format!("any of '{}'", prefixes.join("', '"));
+        self.prefix_matches = Box::new(move |public_key_base58| {
+            prefixes.iter().any(|prefix| public_key_base58.starts_with(prefix))
+        });
+        Ok(self)
+    }
+
+    // Matches a public key whose first `count` characters all belong to one of VANITY_CLASSES.
+    fn prefix_class(mut self, class: &str, count: usize) -> Result<Self, CliError> {
+        let Some((_, in_class)) = VANITY_CLASSES.into_iter().find(|(name, _)| *name == class) else {
+            return Err(CliError::InvalidArgument(format!(
+                "Unknown character class '{}', use one of: {}",
+                class, VANITY_CLASSES.map(|(name, _)| name).join(", ")
+            )));
+        };
+        if self.ignore_case {
+            return Err(CliError::InvalidArgument("--prefix-class can't be combined with --ignore-case".to_string()));
+        }
+        let matching = BASE58_ALPHABET.chars().filter(in_class).count();
+        self.prefix_attempts = (58.0 / matching as f64).powi(count as i32);
+        self.prefix_description = format!("{} {} character(s)", count, class);
+        self.prefix_matches = Box::new(move |public_key_base58| {
+            public_key_base58.chars().take(count).filter(in_class).count() == count
+        });
+        Ok(self)
+    }
+
+    fn describe(&self) -> String {
+        match (self.prefix_description.as_str(), self.suffix.is_empty()) {
+            ("''", _) => format!("ending with '{}'", self.suffix),
+            (prefix, true) => format!("starting with {}", prefix),
+            (prefix, false) => format!("starting with {} and ending with '{}'", prefix, self.suffix),
+        }
+    }
+
+    fn expected_attempts(&self) -> f64 {
+        self.prefix_attempts * expected_attempts(&self.suffix, self.ignore_case)
+    }
+
+    fn attempts(&self) -> u64 {
+        self.attempts.load(Ordering::Relaxed)
+    }
+
+    fn matches(&self, pubkey: &Pubkey) -> bool {
+        let mut public_key_base58 = bs58::encode(pubkey).into_string();
+        if self.ignore_case {
+            public_key_base58 = public_key_base58.to_lowercase();
+        }
+        (self.prefix_matches)(&public_key_base58) && public_key_base58.ends_with(&self.suffix)
+    }
+
+    fn run(&self) -> Option<Keypair> {
+        self.run_with_progress(|_| {})
+    }
+
+    // Calls `progress` with the attempts so far about once a second until a worker finds a
+    // match or all of them pass the deadline.
+    fn run_with_progress(&self, mut progress: impl FnMut(u64)) -> Option<Keypair> {
+        let found = AtomicBool::new(false);
+        let (sender, receiver) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for _ in 0..self.threads {
+                let sender = sender.clone();
+                let found = &found;
+                scope.spawn(move || {
+                    while !found.load(Ordering::Relaxed) && !interrupted() && Instant::now() < self.deadline {
+                        self.attempts.fetch_add(1, Ordering::Relaxed);
+                        let keypair = Keypair::new();
+                        if self.matches(&keypair.pubkey()) {
+                            if !found.swap(true, Ordering::Relaxed) {
+                                let _ = sender.send(keypair);
+                            }
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            // Every worker drops its sender on exit, so this ends with None once all of them time
+            // out or are interrupted.
+            let keypair = loop {
+                match receiver.recv_timeout(Duration::from_secs(1)) {
+                    Ok(keypair) => break Some(keypair),
+                    Err(mpsc::RecvTimeoutError::Timeout) => progress(self.attempts()),
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break None,
+                }
+            };
+            found.store(true, Ordering::Relaxed);
+            keypair
+        })
+    }
+}
+
+// Runs the search printing its progress to stderr every second, when stderr is a terminal.
+fn run_vanity_search(search: &VanitySearch) -> Option<Keypair> {
+    let _interruptible = Interruptible::start();
+    if !std::io::stderr().is_terminal() {
+        return search.run();
+    }
+    let start_time = Instant::now();
+    let mut reported = false;
+    let keypair = search.run_with_progress(|attempts| {
+        let elapsed_time = start_time.elapsed();
+        eprint!(
+            "\r⏳ {} attempts in {}s ({:.0} attempts/s)",
+            attempts,
+            elapsed_time.as_secs(),
+            attempts as f64 / elapsed_time.as_secs_f64()
+        );
+        reported = true;
+    });
+    if reported {
+        eprintln!();
+    }
+    keypair
+}
+
+fn send_sol(config: &Config, recipient: &Pubkey, amount_in_lamports: u64, memo: Option<&str>) -> Result<Option<Signature>, CliError> {
+    if amount_in_lamports == 0 {
+        return Err(CliError::InvalidArgument("Amount must be greater than zero".to_string()));
+    }
+    let amount_sol = amount_in_lamports as f64 / LAMPORTS_PER_SOL as f64;
+    let memo = memo.filter(|memo| !memo.is_empty());
+    let memo_instruction = memo.map(memo_instruction).transpose()?;
+
+    let sender = load_signer(config)?;
+ 
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    config.info(format!("💸 Attempting to send {} SOL to {}...", amount_sol, recipient));
+    confirm(config, &format!("Send {} SOL to {}", amount_sol, recipient))?;
+
+    let mut tx_builder = TxBuilder::new(config, &*sender)
+        .instruction(system_instruction::transfer(&sender.pubkey(), recipient, amount_in_lamports));
+
+    if let Some((memo_instruction, memo_text)) = memo_instruction.zip(memo) {
+        tx_builder = tx_builder.instruction(memo_instruction);
+        config.info(format!("📝 memo is: {}", memo_text));
+    }
+
+    let transaction = tx_builder.build(&connection)?;
+
+    if config.offline.is_none() {
+        check_sufficient_funds(config, &connection, &sender.pubkey(), amount_in_lamports, &transaction)?;
+    }
+
+    let Some(signature) = tx_builder.submit(&connection, &transaction)? else {
+        return Ok(None);
+    };
+
+    config.output(
+        format!("✅ Transaction confirmed, signature: {}!", signature),
+        json!({ "signature": signature.to_string() }),
+    );
+    
+    Ok(Some(signature))
+}
+
+// A memo program instruction carrying `memo`, checked against what the program accepts.
+fn memo_instruction(memo: &str) -> Result<Instruction, CliError> {
+    if memo.len() > MAX_MEMO_BYTES {
+        return Err(CliError::InvalidArgument(format!("Memo is {} bytes long, the memo program accepts at most {}", memo.len(), MAX_MEMO_BYTES)));
+    }
+    let memo_program_id = Pubkey::from_str("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr")?;
+    Ok(Instruction::new_with_bytes(memo_program_id, memo.as_bytes(), vec![]))
+}
+
+fn sweep_sol(config: &Config, recipient: &Pubkey, keep_rent_exempt: bool) -> Result<Option<Signature>, CliError> {
+    let sender = load_signer(config)?;
+
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    let (transaction, amount_in_lamports) = sweep_transaction(config, &connection, &*sender, recipient, keep_rent_exempt)?;
+    let amount_sol = amount_in_lamports as f64 / LAMPORTS_PER_SOL as f64;
+
+    config.info(format!("🧹 Attempting to sweep {} SOL to {}...", amount_sol, recipient));
+    confirm(config, &format!("Send all {} SOL to {}", amount_sol, recipient))?;
+
+    if !preflight(config, &connection, &transaction)? {
+        return Ok(None);
+    }
+
+    let signature = send_and_confirm(config, &connection, &transaction)?;
+
+    config.output(
+        format!("✅ Swept {} SOL, signature: {}!", amount_sol, signature),
+        json!({ "signature": signature.to_string(), "lamports": amount_in_lamports }),
+    );
+
+    Ok(Some(signature))
+}
+
+// The fee doesn't depend on the amount, so it is priced on a transfer of the whole
+// balance and the transfer is then rebuilt with exactly what is left over.
+fn sweep_transaction(
+    config: &Config,
+    connection: &impl SolanaClient,
+    sender: &dyn Signer,
+    recipient: &Pubkey,
+    keep_rent_exempt: bool,
+) -> Result<(Transaction, u64), CliError> {
+    let balance = with_retry(config.max_retries, "getBalance", || connection.get_balance(&sender.pubkey()))?;
+    let recent_blockhash = latest_blockhash(config, connection)?;
+
+    let build = |amount_in_lamports| -> Result<Transaction, CliError> {
+        let mut transaction = new_transaction(
+            config,
+            &[system_instruction::transfer(&sender.pubkey(), recipient, amount_in_lamports)],
+            &sender.pubkey(),
+        );
+        sign_transaction(config, &mut transaction, &[sender], recent_blockhash)?;
+        Ok(transaction)
+    };
+
+    // A separate --fee-payer covers the fee, so everything can go.
+    let transaction = build(balance)?;
+    let fee = if transaction.message.account_keys.first() == Some(&sender.pubkey()) {
+        connection.get_fee_for_message(&transaction.message)?
+    } else {
+        0
+    };
+    let reserve = if keep_rent_exempt {
+        connection.get_minimum_balance_for_rent_exemption(0)?
+    } else {
+        0
+    };
+    let amount_in_lamports = balance
+        .checked_sub(fee + reserve)
+        .filter(|amount| *amount > 0)
+        .ok_or_else(|| format!(
+            "Nothing to sweep: the balance of {} SOL doesn't cover the fee{}",
+            balance as f64 / LAMPORTS_PER_SOL as f64,
+            if keep_rent_exempt { " and the rent exempt minimum" } else { "" }
+        ))?;
+
+    Ok((build(amount_in_lamports)?, amount_in_lamports))
+}
+
+// The sender has to cover the amount and the fee, and stay rent exempt afterwards.
+fn check_sufficient_funds(
+    config: &Config,
+    connection: &impl SolanaClient,
+    sender: &Pubkey,
+    amount_in_lamports: u64,
+    transaction: &Transaction,
+) -> Result<(), CliError> {
+    let balance = with_retry(config.max_retries, "getBalance", || connection.get_balance(sender))?;
+    // A separate --fee-payer covers the fee.
+    let fee = if transaction.message.account_keys.first() == Some(sender) {
+        connection.get_fee_for_message(&transaction.message)?
+    } else {
+        0
+    };
+    let rent_exempt_minimum = connection.get_minimum_balance_for_rent_exemption(0)?;
+    let needed = amount_in_lamports + fee + rent_exempt_minimum;
+    if balance < needed {
+        return Err(CliError::InsufficientFunds { needed, available: balance });
+    }
+    Ok(())
+}
+
+// Reads `pubkey,amount` CSV lines (blank lines and # comments are skipped), or a JSON array of
+// {"pubkey": ..., "amount": ...} objects when the file name ends with .json. `--file -` reads
+// them from stdin instead, as JSON when the input starts with `[`. Each amount goes through
+// `parse_amount`, so SOL and token amounts keep their own units.
+fn read_batch_file<T>(path: &str, parse_amount: fn(&str) -> Result<T, String>) -> Result<Vec<(Pubkey, T)>, CliError> {
+    if path == "-" {
+        let mut contents = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut contents)
+            .map_err(|e| format!("Failed to read stdin: {}", e))?;
+        return parse_batch(&contents, "stdin", contents.trim_start().starts_with('['), parse_amount);
+    }
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    parse_batch(&contents, path, path.ends_with(".json"), parse_amount)
+}
+
+fn parse_batch<T>(
+    contents: &str,
+    source: &str,
+    is_json: bool,
+    parse_amount: fn(&str) -> Result<T, String>,
+) -> Result<Vec<(Pubkey, T)>, CliError> {
+    let entries = if is_json {
+        let values: Vec<serde_json::Value> = serde_json::from_str(contents)
+            .map_err(|e| CliError::InvalidArgument(format!("{} is not a JSON array: {}", source, e)))?;
+        values.iter().enumerate().map(|(index, value)| {
+            // Numbers are read back from their JSON text rather than through f64.
+            let amount = match &value["amount"] {
+                serde_json::Value::Number(number) => Some(number.to_string()),
+                serde_json::Value::String(amount) => Some(amount.clone()),
+                _ => None,
+            };
+            match (value["pubkey"].as_str(), amount) {
+                (Some(pubkey), Some(amount)) => Ok((
+                    Pubkey::from_str(pubkey).map_err(|e| CliError::InvalidPubkey {
+                        value: pubkey.to_string(),
+                        reason: format!("{}, in entry {} of {}", e, index, source),
+                    })?,
+                    parse_amount(&amount)
+                        .map_err(|e| CliError::InvalidArgument(format!("Entry {} of {} has an invalid amount: {}", index, source, e)))?,
+                )),
+                _ => Err(CliError::InvalidArgument(format!("Entry {} of {} needs a \"pubkey\" string and an \"amount\"", index, source))),
+            }
+        }).collect::<Result<Vec<_>, CliError>>()?
+    } else {
+        contents.lines().enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(index, line)| {
+                let (pubkey, amount) = line.split_once(',')
+                    .ok_or_else(|| CliError::InvalidArgument(format!("Line {} of {} is not `pubkey,amount`", index + 1, source)))?;
+                let amount = parse_amount(amount.trim())
+                    .map_err(|e| CliError::InvalidArgument(format!("Line {} of {} has an invalid amount: {}", index + 1, source, e)))?;
+                let pubkey = Pubkey::from_str(pubkey.trim()).map_err(|e| CliError::InvalidPubkey {
+                    value: pubkey.trim().to_string(),
+                    reason: format!("{}, on line {} of {}", e, index + 1, source),
+                })?;
+                Ok((pubkey, amount))
+            }).collect::<Result<Vec<_>, CliError>>()?
+    };
+
+    if entries.is_empty() {
+        return Err(CliError::InvalidArgument(format!("{} lists no recipients", source)));
+    }
+    Ok(entries)
+}
+
+// batch-send amounts, parsed exactly like `send` so no lamport is lost to float rounding.
+fn parse_batch_lamports(value: &str) -> Result<u64, String> {
+    match parse_sol_amount(value)? {
+        0 => Err("it must be greater than zero".to_string()),
+        lamports => Ok(lamports),
+    }
+}
+
+// distribute amounts, in whole tokens until the mint's decimals are known.
+fn parse_batch_tokens(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(amount) if amount.is_finite() && amount > 0.0 => Ok(amount),
+        Ok(_) => Err("it must be greater than zero".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn batch_send_sol(config: &Config, path: &str) -> Result<Vec<Signature>, CliError> {
+    let transfers = read_batch_file(path, parse_batch_lamports)?;
+    let recipient_count = transfers.len();
+    let sender = load_signer(config)?;
+
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    let total_lamports = transfers.iter().try_fold(0u64, |total, (_, lamports)| total.checked_add(*lamports))
+        .ok_or_else(|| CliError::InvalidArgument(format!("The amounts in {} add up to more than fits in a u64", path)))?;
+    let balance = with_retry(config.max_retries, "getBalance", || connection.get_balance(&sender.pubkey()))?;
+    if balance < total_lamports {
+        return Err(CliError::InsufficientFunds { needed: total_lamports, available: balance });
+    }
+
+    confirm(config, &format!(
+        "Send {} SOL in total to {} recipients",
+        total_lamports as f64 / LAMPORTS_PER_SOL as f64,
+        recipient_count
+    ))?;
+
+    let batches = pack_transfers(config, &sender.pubkey(), transfers)?;
+    config.info(format!("📦 Sending {} transfer(s) in {} transaction(s)...", recipient_count, batches.len()));
+
+    let (signatures, results) = send_transfer_batches(config, &connection, &*sender, &batches)?;
+    if !config.sends_transactions() {
+        return Ok(signatures);
+    }
+
+    config.output(
+        format!("✅ Sent {} SOL to {} recipient(s)!", total_lamports as f64 / LAMPORTS_PER_SOL as f64, recipient_count),
+        json!({ "transactions": results }),
+    );
+
+    Ok(signatures)
+}
+
+// A recipient and the lamports sent to it.
+type Transfer = (Pubkey, u64);
+
+fn transfers_transaction(config: &Config, sender: &Pubkey, batch: &[Transfer]) -> Transaction {
+    let instructions: Vec<_> = batch
+        .iter()
+        .map(|(recipient, lamports)| system_instruction::transfer(sender, recipient, *lamports))
+        .collect();
+    new_transaction(config, &instructions, sender)
+}
+
+// Greedily pack transfers while the serialized transaction still fits in a single packet.
+fn pack_transfers(
+    config: &Config,
+    sender: &Pubkey,
+    transfers: Vec<Transfer>,
+) -> Result<Vec<Vec<Transfer>>, CliError> {
+    let mut batches: Vec<Vec<Transfer>> = vec![];
+    let mut batch = vec![];
+    for transfer in transfers {
+        batch.push(transfer);
+        if bincode::serialized_size(&transfers_transaction(config, sender, &batch))? as usize > PACKET_DATA_SIZE {
+            let transfer = batch.pop().unwrap();
+            batches.push(batch);
+            batch = vec![transfer];
+        }
+    }
+    batches.push(batch);
+    Ok(batches)
+}
+
+// Sends one transaction per batch, returning the signatures and a JSON result for each.
+fn send_transfer_batches(
+    config: &Config,
+    connection: &RpcClient,
+    sender: &dyn Signer,
+    batches: &[Vec<Transfer>],
+) -> Result<(Vec<Signature>, Vec<serde_json::Value>), CliError> {
+    check_offline_transaction_count(config, batches.len())?;
+    let mut signatures = vec![];
+    let mut results = vec![];
+    for batch in batches {
+        let mut transaction = transfers_transaction(config, &sender.pubkey(), batch);
+        let recent_blockhash = latest_blockhash(config, connection)?;
+        sign_transaction(config, &mut transaction, &[sender], recent_blockhash)?;
+
+        if !preflight(config, connection, &transaction)? {
+            continue;
+        }
+
+        let signature = send_and_confirm(config, connection, &transaction)?;
+        for (recipient, lamports) in batch {
+            config.info(format!(
+                "✅ Sent {} SOL to {}, signature: {}",
+                *lamports as f64 / LAMPORTS_PER_SOL as f64,
+                recipient,
+                signature
+            ));
+        }
+        signatures.push(signature);
+        results.push(json!({
+            "signature": signature.to_string(),
+            "recipients": batch.iter().map(|(recipient, lamports)| json!({
+                "pubkey": recipient.to_string(),
+                "lamports": lamports,
+            })).collect::<Vec<_>>(),
+        }));
+    }
+    Ok((signatures, results))
+}
+
+// Funds `count` new wallets with `lamports` each to bootstrap tests. The keypair files are
+// written before anything is sent, so no SOL lands in a wallet whose key was lost.
+fn fan_out(
+    config: &Config,
+    count: u32,
+    lamports: u64,
+    out: Option<&str>,
+    force: bool,
+) -> Result<Vec<Signature>, CliError> {
+    let keypairs: Vec<Keypair> = (0..count).map(|_| Keypair::new()).collect();
+    let paths = keypairs.iter().enumerate()
+        .map(|(index, keypair)| match out {
+            Some(path) => config.output_file(&numbered_path(path, index as u32)).map(Some),
+            None => config.default_output_file("keypair", &keypair.pubkey()),
+        })
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .collect::<Option<Vec<String>>>()
+        .ok_or("Pass --out or --output-dir to choose where to write the new keypairs")?;
+    if let Some(path) = paths.iter().find(|path| !force && Path::new(path).exists()) {
+        return Err(format!("{} already exists, pass --force to overwrite it", path).into());
+    }
+
+    let sender = load_signer(config)?;
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    let rent_exempt_minimum = with_retry(config.max_retries, "getMinimumBalanceForRentExemption", || {
+        connection.get_minimum_balance_for_rent_exemption(0)
+    })?;
+    if lamports < rent_exempt_minimum {
+        return Err(CliError::InvalidArgument(format!(
+            "Invalid amount: each new wallet needs at least the rent exempt minimum of {} SOL",
+            rent_exempt_minimum as f64 / LAMPORTS_PER_SOL as f64
+        )));
+    }
+    let total_lamports = lamports.checked_mul(count as u64).ok_or_else(|| CliError::InvalidArgument("Invalid amount: the total overflows".to_string()))?;
+
+    let transfers = keypairs.iter().map(|keypair| (keypair.pubkey(), lamports)).collect();
+    let batches = pack_transfers(config, &sender.pubkey(), transfers)?;
+    let fee_lamports = batches.iter()
+        .map(|batch| connection.get_fee_for_message(&transfers_transaction(config, &sender.pubkey(), batch).message))
+        .sum::<ClientResult<u64>>()?;
+    // Our own account has to stay rent exempt too.
+    let needed = total_lamports + fee_lamports + rent_exempt_minimum;
+    let balance = with_retry(config.max_retries, "getBalance", || connection.get_balance(&sender.pubkey()))?;
+    if balance < needed {
+        return Err(CliError::InsufficientFunds { needed, available: balance });
+    }
+
+    confirm(config, &format!(
+        "Send {} SOL to each of {} new wallets, {} SOL in total plus {} SOL in fees",
+        lamports as f64 / LAMPORTS_PER_SOL as f64,
+        count,
+        total_lamports as f64 / LAMPORTS_PER_SOL as f64,
+        fee_lamports as f64 / LAMPORTS_PER_SOL as f64
+    ))?;
+
+    // --dry-run and --simulate fund nothing, so they only name the files. An --offline transaction
+    // funds the wallets once submitted, so their keys are saved like for a real send.
+    if config.dry_run || config.simulate {
+        config.info(format!("📝 Would write {} keypair(s) to: {}", count, paths.join(", ")));
+    } else {
+        for (keypair, path) in keypairs.iter().zip(&paths) {
+            write_keypair_file(keypair, path, force)?;
+        }
+        config.info(format!("💾 Wrote {} keypair(s), funding them in {} transaction(s)...", count, batches.len()));
+    }
+
+    let (signatures, results) = send_transfer_batches(config, &connection, &*sender, &batches)?;
+    if !config.sends_transactions() {
+        return Ok(signatures);
+    }
+
+    config.output(
+        format!(
+            "✅ Funded {} new wallet(s) with {} SOL in total, fees paid: {} SOL",
+            count,
+            total_lamports as f64 / LAMPORTS_PER_SOL as f64,
+            fee_lamports as f64 / LAMPORTS_PER_SOL as f64
+        ),
+        json!({
+            "wallets": keypairs.iter().zip(&paths).map(|(keypair, path)| json!({
+                "pubkey": keypair.pubkey().to_string(),
+                "file": path,
+            })).collect::<Vec<_>>(),
+            "total_lamports": total_lamports,
+            "fee_lamports": fee_lamports,
+            "transactions": results,
+        }),
+    );
+
+    Ok(signatures)
+}
+
+fn read_transaction_file(path: &str) -> Result<Transaction, CliError> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let bytes = BASE64.decode(contents.trim())
+        .map_err(|e| format!("{} does not contain a base64 transaction: {}", path, e))?;
+    bincode::deserialize(&bytes)
+        .map_err(|e| format!("Failed to decode the transaction in {}: {}", path, e).into())
+}
+
+// Adds the signature of a required signer that hasn't signed yet, keeping the blockhash
+// the transaction was built with.
+fn add_transaction_signature(transaction: &mut Transaction, signer: &dyn Signer) -> Result<(), CliError> {
+    let missing = missing_signers(transaction);
+    if missing.is_empty() {
+        return Err("The transaction is already fully signed".into());
+    }
+    if !missing.contains(&signer.pubkey()) {
+        return Err(format!(
+            "{} is not a missing signer of the transaction, it needs: {}",
+            signer.pubkey(), missing.iter().map(Pubkey::to_string).collect::<Vec<_>>().join(", ")
+        ).into());
+    }
+    let recent_blockhash = transaction.message.recent_blockhash;
+    transaction.try_partial_sign(&[signer], recent_blockhash)?;
+    Ok(())
+}
+
+fn add_signature(config: &Config, path: &str) -> Result<(), CliError> {
+    let mut transaction = read_transaction_file(path)?;
+    let signer = load_signer(config)?;
+    add_transaction_signature(&mut transaction, &*signer)?;
+    fs::write(path, BASE64.encode(bincode::serialize(&transaction)?))
+        .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+    let missing = missing_signers(&transaction);
+    config.output(
+        if missing.is_empty() {
+            format!("✍️ Added the signature of {}, broadcast it with `submit --file {}`", signer.pubkey(), path)
+        } else {
+            format!(
+                "✍️ Added the signature of {}, still missing: {}",
+                signer.pubkey(), missing.iter().map(Pubkey::to_string).collect::<Vec<_>>().join(", ")
+            )
+        },
+        json!({
+            "file": path,
+            "signer": signer.pubkey().to_string(),
+            "missing_signers": missing.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+        }),
+    );
+    Ok(())
+}
+
+fn submit_transaction(config: &Config, path: &str) -> Result<Option<Signature>, CliError> {
+    let transaction = read_transaction_file(path)?;
+    transaction.verify()
+        .map_err(|e| format!("The transaction in {} is not fully signed: {}", path, e))?;
+
+    let connection = create_connection(config);
+
+    if !preflight(config, &connection, &transaction)? {
+        return Ok(None);
+    }
+
+    let signature = send_and_confirm(config, &connection, &transaction)?;
+
+    let explorer_link = config.link("transaction", signature);
+
+    config.output(
+        format!("✅ Transaction confirmed: {}", explorer_link),
+        json!({ "signature": signature.to_string() }),
+    );
+
+    Ok(Some(signature))
+}
+
+fn create_nonce_account(
+    config: &Config,
+    authority: Option<&str>,
+    nonce_keypair: Option<&str>,
+) -> Result<Pubkey, CliError> {
+    let sender = load_signer(config)?;
+    let authority = match authority {
+        Some(authority) => parse_pubkey(authority)?,
+        None => sender.pubkey(),
+    };
+    let nonce_keypair = match nonce_keypair {
+        Some(path) => load_keypair_from_file(path)?,
+        None => save_generated_keypair(config, "nonce", Keypair::new())?,
+    };
+
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    let rent_exempt_balance = connection.get_minimum_balance_for_rent_exemption(nonce::State::size())?;
+    let instructions = system_instruction::create_nonce_account(
+        &sender.pubkey(),
+        &nonce_keypair.pubkey(),
+        &authority,
+        rent_exempt_balance,
+    );
+
+    let tx_builder = TxBuilder::new(config, &*sender)
+        .instructions(instructions)
+        .signer(&nonce_keypair);
+    if tx_builder.send(&connection)?.is_none() {
+        return Ok(nonce_keypair.pubkey());
+    }
+
+    let explorer_link = config.link("address", nonce_keypair.pubkey());
+
+    config.output(
+        format!("✅ Nonce account {} with authority {}: {}", nonce_keypair.pubkey(), authority, explorer_link),
+        json!({ "nonce_account": nonce_keypair.pubkey().to_string(), "authority": authority.to_string() }),
+    );
+
+    Ok(nonce_keypair.pubkey())
+}
+
+fn create_token_mint(
+    config: &Config,
+    decimals: u8,
+    mint_authority: Option<&str>,
+    freeze_authority: Option<&str>,
+    mint_keypair: Option<&str>,
+    mint_prefix: Option<&str>,
+) -> Result<Pubkey, CliError> {
+    if decimals > 9 {
+        return Err(CliError::InvalidArgument(format!("Decimals must be between 0 and 9, got {}", decimals)));
+    }
+    let mint_search = mint_prefix
+        .map(|prefix| VanitySearch::new(prefix, "", false, num_cpus::get(), Duration::from_secs(MINT_PREFIX_TIMEOUT_MINUTES * 60)))
+        .transpose()?;
+
+    let sender = load_signer(config)?;
+    let mint_authority = match mint_authority {
+        Some(mint_authority) => parse_pubkey(mint_authority)?,
+        None => sender.pubkey(),
+    };
+    let freeze_authority = freeze_authority.map(parse_pubkey).transpose()?;
+ 
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    let mint_account = match (mint_keypair, mint_prefix.zip(mint_search)) {
+        (Some(path), _) => load_keypair_from_file(path)?,
+        (None, Some((prefix, mint_search))) => {
+            config.info(format!("🔍 Searching for a mint address {}...", mint_search.describe()));
+            run_vanity_search(&mint_search).ok_or_else(|| -> CliError {
+                if interrupted() {
+                    return CliError::Interrupted(format!(
+                        "Interrupted after {} attempts, no mint address starting with {} was found",
+                        mint_search.attempts(), prefix
+                    ));
+                }
+                format!(
+                    "No mint address starting with {} was found within {} minutes",
+                    prefix, MINT_PREFIX_TIMEOUT_MINUTES
+                ).into()
+            })?
+        }
+        (None, None) => Keypair::new(),
+    };
+    let mint_account = match mint_keypair {
+        Some(_) => mint_account,
+        None => save_generated_keypair(config, "mint", mint_account)?,
+    };
+
+    let mint_pubkey = create_mint(
+        config,
+        &connection,
+        &*sender,
+        &mint_account,
+        &mint_authority,
+        freeze_authority.as_ref(),
+        decimals,
+    )?;
+    if !config.sends_transactions() {
+        return Ok(mint_pubkey);
+    }
+    
+    let explorer_link = config.link("address", mint_pubkey);
+
+    save_state_value("mint", &mint_pubkey.to_string())?;
+    config.info(format!("💾 Saved the mint address to {}", STATE_FILE));
+
+    config.output(
+        format!("✅ Token Mint: {}", explorer_link),
+        json!({ "mint": mint_pubkey.to_string() }),
+    );
+
+    Ok(mint_pubkey)
+}
+
+// Creates the mint in one transaction, then its metadata, our token account and the
+// initial supply in a second one.
+fn launch_token(
+    config: &Config,
+    name: &str,
+    symbol: &str,
+    uri: &str,
+    decimals: u8,
+    supply: f64,
+) -> Result<Pubkey, CliError> {
+    if decimals > 9 {
+        return Err(CliError::InvalidArgument(format!("Decimals must be between 0 and 9, got {}", decimals)));
+    }
+    if !supply.is_finite() || supply <= 0.0 {
+        return Err(CliError::InvalidArgument(format!("Supply must be greater than zero, got {}", supply)));
+    }
+    validate_metadata_field("name", name, MAX_NAME_LENGTH)?;
+    validate_metadata_field("symbol", symbol, MAX_SYMBOL_LENGTH)?;
+    validate_metadata_field("uri", uri, MAX_URI_LENGTH)?;
+    // The mint has to exist before its metadata and supply can be added.
+    check_offline_transaction_count(config, 2)?;
+
+    let sender = load_signer(config)?;
+
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    confirm(config, &format!("Launch {} ({}) with a supply of {}", name, symbol, supply))?;
+
+    let mint_account = save_generated_keypair(config, "mint", Keypair::new())?;
+    let mint_pubkey = create_mint(config, &connection, &*sender, &mint_account, &sender.pubkey(), None, decimals)?;
+    config.info(format!("🪙 Created the token mint {}", mint_pubkey));
+
+    let (token_account, create_ata_instruction) = associated_token_account_instruction(
+        &connection,
+        &sender.pubkey(),
+        &mint_pubkey,
+        &sender.pubkey(),
+        &config.token_program_id,
+    );
+    let mint_to_instruction = mint_to(
+        &config.token_program_id,
+        &mint_pubkey,
+        &token_account,
+        &sender.pubkey(),
+        &[],
+        to_minor_units(supply, decimals),
+    )?;
+    let tx_builder = TxBuilder::new(config, &*sender)
+        .instruction(create_metadata_instruction(&config.metadata_program_id, &mint_pubkey, &sender.pubkey(), name, symbol, uri, 0))
+        .instructions(create_ata_instruction)
+        .instruction(mint_to_instruction);
+    let Some(signature) = tx_builder.send(&connection)? else {
+        return Ok(mint_pubkey);
+    };
+
+    save_state_value("mint", &mint_pubkey.to_string())?;
+    config.info(format!("💾 Saved the mint address to {}", STATE_FILE));
+
+    let balance = connection.get_token_account_balance(&token_account)?;
+    let sol_balance = with_retry(config.max_retries, "getBalance", || connection.get_balance(&sender.pubkey()))?;
+    let metadata_pda = find_metadata_pda(&config.metadata_program_id, &mint_pubkey);
+
+    config.output(
+        format!(
+            "🚀 Launched {} ({})\n  Mint: {}\n  Metadata: {}\n  Token account: {}\n  Token balance: {}\n  SOL balance: {}\n✅ {}",
+            name,
+            symbol,
+            mint_pubkey,
+            metadata_pda,
+            token_account,
+            balance.ui_amount_string,
+            sol_balance as f64 / LAMPORTS_PER_SOL as f64,
+            config.link("address", mint_pubkey)
+        ),
+        json!({
+            "signature": signature.to_string(),
+            "mint": mint_pubkey.to_string(),
+            "metadata": metadata_pda.to_string(),
+            "token_account": token_account.to_string(),
+            "token_balance": balance.ui_amount_string,
+            "lamports": sol_balance,
+        }),
+    );
+
+    Ok(mint_pubkey)
+}
+
+fn create_mint(
+    config: &Config,
+    connection: &impl SolanaClient,
+    payer: &dyn Signer,
+    mint_account: &dyn Signer,
+    mint_authority: &Pubkey,
+    freeze_authority: Option<&Pubkey>,
+    decimals: u8,
+) -> Result<Pubkey, CliError> {
+    let mint_pubkey = mint_account.pubkey();
+    // A retry after a confirmation timeout finds the mint the first attempt already created.
+    if let Ok(existing_account) = connection.get_account(&mint_pubkey) {
+        let existing_mint = (existing_account.owner == config.token_program_id)
+            .then(|| fetch_mint(connection, &mint_pubkey).ok())
+            .flatten()
+            .filter(|mint| mint.is_initialized)
+            .ok_or_else(|| format!("Account {} already exists and is not a token mint", mint_pubkey))?;
+        if existing_mint.decimals != decimals
+            || existing_mint.mint_authority != COption::Some(*mint_authority)
+            || existing_mint.freeze_authority != freeze_authority.copied().into()
+        {
+            return Err(format!(
+                "Mint {} already exists with other decimals or authorities, it can't be created again",
+                mint_pubkey
+            ).into());
+        }
+        config.info(format!("♻️ Mint {} already exists, nothing to create", mint_pubkey));
+        return Ok(mint_pubkey);
+    }
+    let mint_rent_exempt_balance = connection.get_minimum_balance_for_rent_exemption(Mint::LEN)?;
+
+    let create_account_instruction = solana_sdk::system_instruction::create_account(
+        &payer.pubkey(),
+        &mint_pubkey,
+        mint_rent_exempt_balance,
+        Mint::LEN as u64,
+        &config.token_program_id,
+    );
+
+    let mint_instruction = initialize_mint(
+        &config.token_program_id,
+        &mint_pubkey,
+        mint_authority,
+        freeze_authority,
+        decimals,
+    )?;
+
+    TxBuilder::new(config, payer)
+        .instruction(create_account_instruction)
+        .instruction(mint_instruction)
+        .signer(mint_account)
+        .send(connection)?;
+
+    Ok(mint_pubkey)
+}
+
+fn create_token_account(
+    config: &Config,
+    mint: Option<&str>,
+    owner: Option<&str>,
+) -> Result<Pubkey, CliError> {
+    let sender = load_signer(config)?;
+    let token_mint_account = resolve_mint(mint)?;
+    let recipient = match owner {
+        Some(owner) => parse_pubkey(owner)?,
+        None => sender.pubkey(),
+    };
+ 
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    let account_pubkey = get_or_create_associated_token_account(
+        config,
+        &connection,
+        &*sender,
+        &token_mint_account,
+        &recipient,
+        &config.token_program_id,
+    )?;
+    if !config.sends_transactions() {
+        return Ok(account_pubkey);
+    }
+
+    let explorer_link = config.link("address", account_pubkey);
+
+    config.output(
+        format!("Token Account: {}\n✅ Created token account: {}", account_pubkey, explorer_link),
+        json!({ "token_account": account_pubkey.to_string() }),
+    );
+
+    Ok(account_pubkey)
+}
+
+fn parse_seed(seed: &str) -> Result<Vec<u8>, CliError> {
+    let bytes = if let Some(hex) = seed.strip_prefix("hex:") {
+        let hex = hex.trim_start_matches("0x");
+        if hex.len() % 2 != 0 {
+            return Err(CliError::InvalidArgument(format!("Invalid hex seed '{}': it has an odd number of digits", hex)));
+        }
+        (0..hex.len()).step_by(2)
+            .map(|i| u8::from_str_radix(hex.get(i..i + 2).unwrap_or_default(), 16))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| CliError::InvalidArgument(format!("Invalid hex seed '{}': {}", hex, e)))?
+    } else if let Some(pubkey) = seed.strip_prefix("pubkey:") {
+        parse_pubkey(pubkey)?.to_bytes().to_vec()
+    } else {
+        seed.strip_prefix("str:").unwrap_or(seed).as_bytes().to_vec()
+    };
+    if bytes.len() > MAX_SEED_LEN {
+        return Err(CliError::InvalidArgument(format!("Invalid seed '{}': it is {} bytes long, seeds are at most {}", seed, bytes.len(), MAX_SEED_LEN)));
+    }
+    Ok(bytes)
+}
+
+fn find_pda(config: &Config, program: &str, seeds: &[&str]) -> Result<(), CliError> {
+    let program_id = parse_pubkey(program)?;
+    let seeds = seeds.iter().map(|seed| parse_seed(seed)).collect::<Result<Vec<_>, _>>()?;
+    // The bump takes up the last of the MAX_SEEDS slots.
+    if seeds.len() >= MAX_SEEDS {
+        return Err(CliError::InvalidArgument(format!("Invalid seeds: got {}, at most {} fit next to the bump", seeds.len(), MAX_SEEDS - 1)));
+    }
+    let seed_slices: Vec<&[u8]> = seeds.iter().map(Vec::as_slice).collect();
+    let (pda, bump) = Pubkey::try_find_program_address(&seed_slices, &program_id)
+        .ok_or("No bump puts these seeds off the ed25519 curve")?;
+
+    config.output(
+        format!("📍 Program derived address: {} (bump {})", pda, bump),
+        json!({ "pubkey": pda.to_string(), "bump": bump, "program": program_id.to_string() }),
+    );
+    Ok(())
+}
+
+fn derive_associated_token_address(
+    config: &Config,
+    mint: Option<&str>,
+    owner: Option<&str>,
+) -> Result<(), CliError> {
+    let token_mint_account = resolve_mint(mint)?;
+    let owner = match owner {
+        Some(owner) => parse_pubkey(owner)?,
+        None => load_signer(config)?.pubkey(),
+    };
+
+    let associated_token_address = get_associated_token_address_with_program_id(
+        &owner,
+        &token_mint_account,
+        &config.token_program_id,
+    );
+
+    let connection = create_connection(config);
+    let exists = connection.get_account(&associated_token_address).is_ok();
+
+    config.output(
+        format!(
+            "🔗 Associated token address: {} ({})",
+            associated_token_address,
+            if exists { "exists" } else { "not created yet" }
+        ),
+        json!({ "token_account": associated_token_address.to_string(), "exists": exists }),
+    );
+
+    Ok(())
+}
+
+fn get_or_create_associated_token_account(
+    config: &Config,
+    connection: &impl SolanaClient,
+    sender: &dyn Signer,
+    mint: &Pubkey,
+    recipient: &Pubkey,
+    token_program_id: &Pubkey,
+) -> Result<Pubkey, CliError> {
+    let (associated_token_address, create_ata_instruction) =
+        associated_token_account_instruction(connection, &sender.pubkey(), mint, recipient, token_program_id);
+
+    if let Some(create_ata_instruction) = create_ata_instruction {
+        TxBuilder::new(config, sender).instruction(create_ata_instruction).send(connection)?;
+    }
+
+    Ok(associated_token_address)
+}
+
+// Returns the associated token account of the owner and, if it doesn't exist yet,
+// the instruction creating it.
+fn associated_token_account_instruction(
+    connection: &impl SolanaClient,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    owner: &Pubkey,
+    token_program_id: &Pubkey,
+) -> (Pubkey, Option<Instruction>) {
+    let associated_token_address = get_associated_token_address_with_program_id(owner, mint, token_program_id);
+    let create_ata_instruction = connection.get_account(&associated_token_address).is_err().then(|| {
+        create_associated_token_account_idempotent(payer, owner, mint, token_program_id)
+    });
+    (associated_token_address, create_ata_instruction)
+}
+
+fn mint_tokens(
+    config: &Config,
+    mint: Option<&str>,
+    to: Option<&str>,
+    amount: f64,
+    signer_paths: &[&str],
+) -> Result<Option<Signature>, CliError> {
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err(CliError::InvalidArgument(format!("Amount must be greater than zero, got {}", amount)));
+    }
+
+    let sender = load_signer(config)?;
+    let multisig_signers = signer_paths
+        .iter()
+        .map(|path| load_keypair_from_file(path))
+        .collect::<Result<Vec<Keypair>, _>>()?;
+    let token_mint_account = resolve_mint(mint)?;
+    let to = match to {
+        Some(to) => parse_pubkey(to)?,
+        None => sender.pubkey(),
+    };
+
+    let connection = create_connection(config);
+    mint_tokens_to(config, &connection, &*sender, token_mint_account, to, amount, &multisig_signers)
+}
+
+fn mint_tokens_to(
+    config: &Config,
+    connection: &impl SolanaClient,
+    sender: &dyn Signer,
+    token_mint_account: Pubkey,
+    to: Pubkey,
+    amount: f64,
+    multisig_signers: &[Keypair],
+) -> Result<Option<Signature>, CliError> {
+    let mint_state = fetch_mint(connection, &token_mint_account)?;
+    let mint_authority = if multisig_signers.is_empty() {
+        if mint_state.mint_authority != COption::Some(sender.pubkey()) {
+            return Err(format!(
+                "{} is not the mint authority of {}",
+                sender.pubkey(), token_mint_account
+            ).into());
+        }
+        sender.pubkey()
+    } else {
+        let COption::Some(mint_authority) = mint_state.mint_authority else {
+            return Err(format!("{} has no mint authority", token_mint_account).into());
+        };
+        check_multisig_signers(connection, &mint_authority, multisig_signers)?;
+        mint_authority
+    };
+
+    // --to may already be a token account of this mint, otherwise it names the owner of one.
+    // A missing associated token account is created in the same transaction as the mint.
+    let (recipient_associated_token_account, create_ata_instruction) = match connection.get_account(&to) {
+        Ok(account) if account.owner == config.token_program_id
+            && StateWithExtensions::<TokenAccount>::unpack(&account.data).is_ok() => (to, None),
+        _ => associated_token_account_instruction(connection, &sender.pubkey(), &token_mint_account, &to, &config.token_program_id),
+    };
+    if create_ata_instruction.is_some() {
+        config.info(format!("🏦 Creating the token account {} for {} in the same transaction", recipient_associated_token_account, to));
+    }
+
+    let amount_in_minor_units = to_minor_units(amount, mint_state.decimals);
+
+    let multisig_signer_pubkeys: Vec<Pubkey> = multisig_signers.iter().map(|signer| signer.pubkey()).collect();
     let mint_to_instruction = mint_to(
-        &spl_token::id(),
+        &config.token_program_id,
+        &token_mint_account,
+        &recipient_associated_token_account,
+        &mint_authority,
+        &multisig_signer_pubkeys.iter().collect::<Vec<_>>(),
+        amount_in_minor_units,
+    )?;
+
+    let tx_builder = TxBuilder::new(config, sender)
+        .instructions(create_ata_instruction)
+        .instruction(mint_to_instruction);
+    let tx_builder = multisig_signers.iter().fold(tx_builder, |tx_builder, signer| tx_builder.signer(signer));
+    let Some(signature) = tx_builder.send(connection)? else {
+        return Ok(None);
+    };
+
+    let explorer_link = config.link("transaction", signature);
+
+    config.output(
+        format!("✅ Success! Mint Token Transaction: {}", explorer_link),
+        json!({ "signature": signature.to_string() }),
+    );
+
+    Ok(Some(signature))
+}
+
+fn distribute_tokens(config: &Config, mint: Option<&str>, path: &str) -> Result<Vec<Signature>, CliError> {
+    let recipients = read_batch_file(path, parse_batch_tokens)?;
+    let sender = load_signer(config)?;
+    let token_mint_account = resolve_mint(mint)?;
+
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    let total: f64 = recipients.iter().map(|(_, amount)| amount).sum();
+    confirm(config, &format!(
+        "Distribute {} tokens of mint {} to {} recipients",
+        total, token_mint_account, recipients.len()
+    ))?;
+
+    let results = distribute(config, &connection, &*sender, &token_mint_account, &recipients)?;
+    if !config.sends_transactions() {
+        return Ok(vec![]);
+    }
+
+    let mut human = vec![];
+    let mut failed = 0;
+    for ((owner, amount), result) in recipients.iter().zip(&results) {
+        match result {
+            Ok(signature) => human.push(format!("✅ {} tokens to {}, signature: {}", amount, owner, signature)),
+            Err(e) => {
+                failed += 1;
+                human.push(format!("❌ {} tokens to {} failed: {}", amount, owner, e));
+            }
+        }
+    }
+    config.output(
+        human.join("\n"),
+        json!(recipients.iter().zip(&results).map(|((owner, amount), result)| match result {
+            Ok(signature) => json!({ "pubkey": owner.to_string(), "amount": amount, "signature": signature.to_string() }),
+            Err(e) => json!({ "pubkey": owner.to_string(), "amount": amount, "error": e }),
+        }).collect::<Vec<_>>()),
+    );
+
+    if failed > 0 {
+        return Err(format!("{} of {} transfers failed", failed, recipients.len()).into());
+    }
+    let mut signatures: Vec<Signature> = results.into_iter().flatten().collect();
+    signatures.dedup();
+    Ok(signatures)
+}
+
+// Mints when we are the mint authority and transfers from our token account otherwise,
+// creating missing token accounts on the way. Recipients are packed into as few
+// transactions as fit; a failed transaction only fails the recipients it carried.
+fn distribute(
+    config: &Config,
+    connection: &impl SolanaClient,
+    sender: &dyn Signer,
+    token_mint_account: &Pubkey,
+    recipients: &[(Pubkey, f64)],
+) -> Result<Vec<Result<Signature, String>>, CliError> {
+    let mint_state = fetch_mint(connection, token_mint_account)?;
+    let is_mint_authority = mint_state.mint_authority == COption::Some(sender.pubkey());
+    let source_token_account = get_associated_token_address_with_program_id(
+        &sender.pubkey(),
+        token_mint_account,
+        &config.token_program_id,
+    );
+
+    let mut recipient_instructions = vec![];
+    for (owner, amount) in recipients {
+        let amount_in_minor_units = to_minor_units(*amount, mint_state.decimals);
+        let (token_account, create_ata_instruction) = associated_token_account_instruction(
+            connection,
+            &sender.pubkey(),
+            token_mint_account,
+            owner,
+            &config.token_program_id,
+        );
+        let mut instructions: Vec<Instruction> = create_ata_instruction.into_iter().collect();
+        instructions.push(if is_mint_authority {
+            mint_to(
+                &config.token_program_id,
+                token_mint_account,
+                &token_account,
+                &sender.pubkey(),
+                &[],
+                amount_in_minor_units,
+            )?
+        } else {
+            transfer_checked(
+                &config.token_program_id,
+                &source_token_account,
+                token_mint_account,
+                &token_account,
+                &sender.pubkey(),
+                &[],
+                amount_in_minor_units,
+                mint_state.decimals,
+            )?
+        });
+        recipient_instructions.push(instructions);
+    }
+
+    // Greedily pack recipients while the serialized transaction still fits in a single packet.
+    let build_transaction = |batch: &[Vec<Instruction>]| {
+        new_transaction(config, &batch.concat(), &sender.pubkey())
+    };
+    let mut batches: Vec<&[Vec<Instruction>]> = vec![];
+    let mut start = 0;
+    for end in 1..=recipient_instructions.len() {
+        if end - start > 1 && bincode::serialized_size(&build_transaction(&recipient_instructions[start..end]))? as usize > PACKET_DATA_SIZE {
+            batches.push(&recipient_instructions[start..end - 1]);
+            start = end - 1;
+        }
+    }
+    batches.push(&recipient_instructions[start..]);
+    config.info(format!(
+        "📦 {} tokens to {} recipient(s) in {} transaction(s)...",
+        if is_mint_authority { "Minting" } else { "Transferring" },
+        recipients.len(),
+        batches.len()
+    ));
+    check_offline_transaction_count(config, batches.len())?;
+
+    let mut results = vec![];
+    for batch in batches {
+        let mut transaction = build_transaction(batch);
+        let recent_blockhash = latest_blockhash(config, connection)?;
+        sign_transaction(config, &mut transaction, &[sender], recent_blockhash)?;
+
+        let result = match preflight(config, connection, &transaction) {
+            Ok(true) => send_and_confirm(config, connection, &transaction)
+                .map_err(|e| e.to_string()),
+            Ok(false) => Err("not sent".to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        results.extend(batch.iter().map(|_| result.clone()));
+    }
+
+    Ok(results)
+}
+
+// Makes sure the keypairs belong to the multisig and are enough to reach its threshold.
+fn check_multisig_signers(
+    connection: &impl SolanaClient,
+    multisig: &Pubkey,
+    signers: &[Keypair],
+) -> Result<(), CliError> {
+    let account = connection.get_account(multisig)?;
+    let multisig_state = Multisig::unpack(&account.data)
+        .map_err(|_| format!("Mint authority {} is not a multisig account", multisig))?;
+    let members = &multisig_state.signers[..multisig_state.n as usize];
+
+    for signer in signers {
+        if !members.contains(&signer.pubkey()) {
+            return Err(format!("{} is not a signer of multisig {}", signer.pubkey(), multisig).into());
+        }
+    }
+    if signers.len() < multisig_state.m as usize {
+        return Err(format!(
+            "Multisig {} needs {} signers, got {}",
+            multisig, multisig_state.m, signers.len()
+        ).into());
+    }
+    Ok(())
+}
+
+fn fetch_mint(connection: &impl SolanaClient, mint: &Pubkey) -> Result<Mint, CliError> {
+    let mint_account = connection.get_account(mint)?;
+    StateWithExtensions::<Mint>::unpack(&mint_account.data)
+        .map(|state| state.base)
+        .map_err(|e| format!("{} is not a token mint: {}", mint, e).into())
+}
+
+fn fetch_mint_decimals(connection: &impl SolanaClient, mint: &Pubkey) -> Result<u8, CliError> {
+    Ok(fetch_mint(connection, mint)?.decimals)
+}
+
+fn show_mint(config: &Config, mint: Option<&str>) -> Result<(), CliError> {
+    let token_mint_account = resolve_mint(mint)?;
+
+    let connection = create_connection(config);
+
+    let mint_state = fetch_mint(&connection, &token_mint_account)?;
+    let supply = mint_state.supply as f64 / 10_u64.pow(mint_state.decimals as u32) as f64;
+    let mint_authority = Option::<Pubkey>::from(mint_state.mint_authority).map(|authority| authority.to_string());
+    let freeze_authority = Option::<Pubkey>::from(mint_state.freeze_authority).map(|authority| authority.to_string());
+
+    config.output(
+        format!(
+            "🪙 Token mint {}:\n  Supply: {}\n  Decimals: {}\n  Mint authority: {}\n  Freeze authority: {}\n  Initialized: {}",
+            token_mint_account,
+            supply,
+            mint_state.decimals,
+            mint_authority.as_deref().unwrap_or("none"),
+            freeze_authority.as_deref().unwrap_or("none"),
+            mint_state.is_initialized
+        ),
+        json!({
+            "mint": token_mint_account.to_string(),
+            "supply": mint_state.supply,
+            "decimals": mint_state.decimals,
+            "mint_authority": mint_authority,
+            "freeze_authority": freeze_authority,
+            "is_initialized": mint_state.is_initialized,
+        }),
+    );
+
+    Ok(())
+}
+
+fn list_token_accounts(config: &Config, owner: Option<&str>) -> Result<(), CliError> {
+    let owner = match owner {
+        Some(owner) => parse_pubkey(owner)?,
+        None => load_signer(config)?.pubkey(),
+    };
+
+    let connection = create_connection(config);
+    config.info(format!("⚡️ Connected to {}", config.rpc_url));
+
+    let token_accounts = with_retry(config.max_retries, "getTokenAccountsByOwner", || {
+        connection.get_token_accounts_by_owner(&owner, TokenAccountsFilter::ProgramId(config.token_program_id))
+    })?;
+
+    let mut human = vec![format!("🗂️ {} token account(s) owned by {}:", token_accounts.len(), owner)];
+    let mut json = vec![];
+    for token_account in token_accounts {
+        let UiAccountData::Json(parsed_account) = token_account.account.data else {
+            continue;
+        };
+        let info = &parsed_account.parsed["info"];
+        let mint = info["mint"].as_str().unwrap_or_default();
+        let amount = info["tokenAmount"]["uiAmountString"].as_str().unwrap_or_default();
+        let frozen = info["state"].as_str() == Some("frozen");
+
+        human.push(format!(
+            "  {:<44}  mint {:<44}  balance {}{}",
+            token_account.pubkey, mint, amount, if frozen { "  (frozen)" } else { "" }
+        ));
+        json.push(json!({
+            "token_account": token_account.pubkey,
+            "mint": mint,
+            "amount": amount,
+            "frozen": frozen,
+        }));
+    }
+    config.output(human.join("\n"), json!(json));
+
+    Ok(())
+}
+
+fn to_minor_units(amount: f64, decimals: u8) -> u64 {
+    (amount * 10_u64.pow(decimals as u32) as f64).round() as u64
+}
+
+fn transfer_tokens(
+    config: &Config,
+    mint: Option<&str>,
+    to_owner: &str,
+    amount: f64,
+    memo: Option<&str>,
+) -> Result<Option<Signature>, CliError> {
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err(CliError::InvalidArgument(format!("Amount must be greater than zero, got {}", amount)));
+    }
+    let memo = memo.filter(|memo| !memo.is_empty());
+    let memo_instruction = memo.map(memo_instruction).transpose()?;
+
+    let sender = load_signer(config)?;
+    let token_mint_account = resolve_mint(mint)?;
+    let recipient = parse_pubkey(to_owner)?;
+
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    confirm(config, &format!("Transfer {} tokens of mint {} to {}", amount, token_mint_account, recipient))?;
+
+    let decimals = fetch_mint_decimals(&connection, &token_mint_account)?;
+    let amount_in_minor_units = to_minor_units(amount, decimals);
+
+    let source_token_account = get_associated_token_address_with_program_id(
+        &sender.pubkey(),
         &token_mint_account,
-        &recipient_associated_token_account,
+        &config.token_program_id,
+    );
+    let (destination_token_account, create_ata_instruction) = associated_token_account_instruction(
+        &connection,
+        &sender.pubkey(),
+        &token_mint_account,
+        &recipient,
+        &config.token_program_id,
+    );
+
+    config.info(format!("💸 Attempting to send {} tokens to {}...", amount, recipient));
+
+    let transfer_instruction = transfer_checked(
+        &config.token_program_id,
+        &source_token_account,
+        &token_mint_account,
+        &destination_token_account,
+        &sender.pubkey(),
+        &[],
+        amount_in_minor_units,
+        decimals,
+    )?;
+    // A missing associated token account is created in the same transaction as the transfer.
+    let mut tx_builder = TxBuilder::new(config, &*sender)
+        .instructions(create_ata_instruction)
+        .instruction(transfer_instruction);
+    if let Some((memo_instruction, memo)) = memo_instruction.zip(memo) {
+        tx_builder = tx_builder.instruction(memo_instruction);
+        config.info(format!("📝 memo is: {}", memo));
+    }
+
+    let Some(signature) = tx_builder.send(&connection)? else {
+        return Ok(None);
+    };
+
+    let explorer_link = config.link("transaction", signature);
+
+    config.output(
+        format!("✅ Success! Transfer Token Transaction: {}", explorer_link),
+        json!({ "signature": signature.to_string() }),
+    );
+
+    Ok(Some(signature))
+}
+
+fn burn_tokens(config: &Config, mint: Option<&str>, amount: f64) -> Result<Option<Signature>, CliError> {
+    if !amount.is_finite() || amount <= 0.0 {
+        return Err(CliError::InvalidArgument(format!("Amount must be greater than zero, got {}", amount)));
+    }
+
+    let sender = load_signer(config)?;
+    let token_mint_account = resolve_mint(mint)?;
+
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    confirm(config, &format!("Burn {} tokens of mint {}", amount, token_mint_account))?;
+
+    let decimals = fetch_mint_decimals(&connection, &token_mint_account)?;
+    let amount_in_minor_units = to_minor_units(amount, decimals);
+
+    let token_account = get_associated_token_address_with_program_id(
+        &sender.pubkey(),
+        &token_mint_account,
+        &config.token_program_id,
+    );
+    let balance = connection.get_token_account_balance(&token_account)
+        .map_err(|e| format!("Failed to read the balance of token account {}: {}", token_account, e))?;
+    if balance.amount.parse::<u64>()? < amount_in_minor_units {
+        return Err(CliError::InsufficientTokens(format!(
+            "Insufficient token balance: want to burn {}, have {}",
+            amount, balance.ui_amount_string
+        )));
+    }
+
+    config.info(format!("🔥 Attempting to burn {} tokens from {}...", amount, token_account));
+
+    let burn_instruction = burn_checked(
+        &config.token_program_id,
+        &token_account,
+        &token_mint_account,
+        &sender.pubkey(),
+        &[],
+        amount_in_minor_units,
+        decimals,
+    )?;
+
+    let tx_builder = TxBuilder::new(config, &*sender).instruction(burn_instruction);
+    let Some(signature) = tx_builder.send(&connection)? else {
+        return Ok(None);
+    };
+
+    let explorer_link = config.link("transaction", signature);
+
+    config.output(
+        format!("✅ Success! Burn Token Transaction: {}", explorer_link),
+        json!({ "signature": signature.to_string() }),
+    );
+
+    Ok(Some(signature))
+}
+
+fn close_token_account(config: &Config, mint: Option<&str>) -> Result<Option<Signature>, CliError> {
+    let sender = load_signer(config)?;
+    let token_mint_account = resolve_mint(mint)?;
+
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    let token_account = get_associated_token_address_with_program_id(
+        &sender.pubkey(),
+        &token_mint_account,
+        &config.token_program_id,
+    );
+    let balance = connection.get_token_account_balance(&token_account)
+        .map_err(|e| format!("Failed to read the balance of token account {}: {}", token_account, e))?;
+    if balance.amount != "0" {
+        return Err(format!(
+            "Token account {} still holds {} tokens, transfer or burn them before closing it",
+            token_account, balance.ui_amount_string
+        ).into());
+    }
+    let rent_in_lamports = with_retry(config.max_retries, "getBalance", || connection.get_balance(&token_account))?;
+
+    let close_instruction = close_account(
+        &config.token_program_id,
+        &token_account,
+        &sender.pubkey(),
+        &sender.pubkey(),
+        &[],
+    )?;
+
+    let tx_builder = TxBuilder::new(config, &*sender).instruction(close_instruction);
+    let Some(signature) = tx_builder.send(&connection)? else {
+        return Ok(None);
+    };
+
+    let explorer_link = config.link("transaction", signature);
+
+    config.output(
+        format!(
+            "♻️ Recovered {} SOL of rent from {}\n✅ Success! Close Token Account Transaction: {}",
+            rent_in_lamports as f64 / LAMPORTS_PER_SOL as f64,
+            token_account,
+            explorer_link
+        ),
+        json!({
+            "signature": signature.to_string(),
+            "token_account": token_account.to_string(),
+            "recovered_lamports": rent_in_lamports,
+        }),
+    );
+
+    Ok(Some(signature))
+}
+
+fn set_mint_authority(
+    config: &Config,
+    mint: Option<&str>,
+    authority_type: &str,
+    new_authority: Option<&str>,
+) -> Result<Option<Signature>, CliError> {
+    let sender = load_signer(config)?;
+    let token_mint_account = resolve_mint(mint)?;
+    let new_authority = new_authority.map(parse_pubkey).transpose()?;
+
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    let mint_state = fetch_mint(&connection, &token_mint_account)?;
+    let (current_authority, authority_type_value) = match authority_type {
+        "mint" => (mint_state.mint_authority, AuthorityType::MintTokens),
+        _ => (mint_state.freeze_authority, AuthorityType::FreezeAccount),
+    };
+    if current_authority != COption::Some(sender.pubkey()) {
+        return Err(format!(
+            "{} is not the {} authority of {}",
+            sender.pubkey(), authority_type, token_mint_account
+        ).into());
+    }
+
+    confirm(config, &match new_authority {
+        Some(new_authority) => format!("Hand the {} authority of {} over to {}", authority_type, token_mint_account, new_authority),
+        None => format!("Revoke the {} authority of {} for good", authority_type, token_mint_account),
+    })?;
+
+    let set_authority_instruction = set_authority(
+        &config.token_program_id,
+        &token_mint_account,
+        new_authority.as_ref(),
+        authority_type_value,
+        &sender.pubkey(),
+        &[],
+    )?;
+
+    let tx_builder = TxBuilder::new(config, &*sender).instruction(set_authority_instruction);
+    let Some(signature) = tx_builder.send(&connection)? else {
+        return Ok(None);
+    };
+
+    let explorer_link = config.link("transaction", signature);
+
+    config.output(
+        format!(
+            "✅ The {} authority of {} was {}: {}",
+            authority_type,
+            token_mint_account,
+            match new_authority {
+                Some(new_authority) => format!("set to {}", new_authority),
+                None => "revoked".to_string(),
+            },
+            explorer_link
+        ),
+        json!({
+            "signature": signature.to_string(),
+            "new_authority": new_authority.map(|new_authority| new_authority.to_string()),
+        }),
+    );
+
+    Ok(Some(signature))
+}
+
+fn native_mint(config: &Config) -> Pubkey {
+    if config.token_program_id == spl_token_2022::id() {
+        spl_token_2022::native_mint::id()
+    } else {
+        spl_token::native_mint::id()
+    }
+}
+
+fn wrap_sol(config: &Config, amount_in_lamports: u64) -> Result<Option<Signature>, CliError> {
+    if amount_in_lamports == 0 {
+        return Err(CliError::InvalidArgument("Amount must be greater than zero".to_string()));
+    }
+    let amount_sol = amount_in_lamports as f64 / LAMPORTS_PER_SOL as f64;
+
+    let sender = load_signer(config)?;
+
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    let (wrapped_sol_account, create_ata_instruction) = associated_token_account_instruction(
+        &connection,
+        &sender.pubkey(),
+        &native_mint(config),
+        &sender.pubkey(),
+        &config.token_program_id,
+    );
+
+    // Lamports sent to a native token account only count as tokens after sync_native.
+    let transfer_instruction = system_instruction::transfer(
+        &sender.pubkey(),
+        &wrapped_sol_account,
+        amount_in_lamports,
+    );
+    let sync_native_instruction = sync_native(&config.token_program_id, &wrapped_sol_account)?;
+    let tx_builder = TxBuilder::new(config, &*sender)
+        .instructions(create_ata_instruction)
+        .instruction(transfer_instruction)
+        .instruction(sync_native_instruction);
+    let Some(signature) = tx_builder.send(&connection)? else {
+        return Ok(None);
+    };
+
+    let explorer_link = config.link("transaction", signature);
+
+    config.output(
+        format!("✅ Wrapped {} SOL into {}: {}", amount_sol, wrapped_sol_account, explorer_link),
+        json!({ "signature": signature.to_string(), "token_account": wrapped_sol_account.to_string() }),
+    );
+
+    Ok(Some(signature))
+}
+
+fn unwrap_sol(config: &Config) -> Result<Option<Signature>, CliError> {
+    let sender = load_signer(config)?;
+
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
+
+    let wrapped_sol_account = get_associated_token_address_with_program_id(
+        &sender.pubkey(),
+        &native_mint(config),
+        &config.token_program_id,
+    );
+    let lamports = with_retry(config.max_retries, "getBalance", || connection.get_balance(&wrapped_sol_account))?;
+    if lamports == 0 {
+        return Err(format!("There is no wrapped SOL account at {}", wrapped_sol_account).into());
+    }
+
+    let close_instruction = close_account(
+        &config.token_program_id,
+        &wrapped_sol_account,
+        &sender.pubkey(),
         &sender.pubkey(),
         &[],
-        10 * MINOR_UNITS_PER_MAJOR_UNITS,
     )?;
 
-    let mut transaction = Transaction::new_with_payer(
-        &[mint_to_instruction],
-        Some(&sender.pubkey()),
+    let tx_builder = TxBuilder::new(config, &*sender).instruction(close_instruction);
+    let Some(signature) = tx_builder.send(&connection)? else {
+        return Ok(None);
+    };
+
+    let explorer_link = config.link("transaction", signature);
+
+    config.output(
+        format!(
+            "✅ Unwrapped {} SOL (including rent) from {}: {}",
+            lamports as f64 / LAMPORTS_PER_SOL as f64,
+            wrapped_sol_account,
+            explorer_link
+        ),
+        json!({ "signature": signature.to_string(), "recovered_lamports": lamports }),
     );
 
-    let recent_blockhash = connection.get_latest_blockhash()?;
-    transaction.sign(&[&sender], recent_blockhash);
-    let signature = connection.send_and_confirm_transaction(&transaction)?;
+    Ok(Some(signature))
+}
+
+fn set_account_frozen(
+    config: &Config,
+    mint: Option<&str>,
+    account: &str,
+    freeze: bool,
+) -> Result<Option<Signature>, CliError> {
+    let sender = load_signer(config)?;
+    let token_mint_account = resolve_mint(mint)?;
+    let token_account = parse_pubkey(account)?;
+
+    let connection = create_connection(config);
+    config.info(format!("🔑 Our public key is: {}", sender.pubkey()));
 
-    let explorer_link = format!(
-        "https://explorer.solana.com/transaction/{}?cluster=devnet",
-        signature
+    let mint_state = fetch_mint(&connection, &token_mint_account)?;
+    if mint_state.freeze_authority != COption::Some(sender.pubkey()) {
+        return Err(format!(
+            "{} is not the freeze authority of {}",
+            sender.pubkey(), token_mint_account
+        ).into());
+    }
+
+    let instruction = if freeze {
+        freeze_account(&config.token_program_id, &token_account, &token_mint_account, &sender.pubkey(), &[])?
+    } else {
+        thaw_account(&config.token_program_id, &token_account, &token_mint_account, &sender.pubkey(), &[])?
+    };
+
+    let tx_builder = TxBuilder::new(config, &*sender).instruction(instruction);
+    let Some(signature) = tx_builder.send(&connection)? else {
+        return Ok(None);
+    };
+
+    let explorer_link = config.link("transaction", signature);
+
+    config.output(
+        format!(
+            "✅ Success! {} Token Account Transaction: {}",
+            if freeze { "Freeze" } else { "Thaw" },
+            explorer_link
+        ),
+        json!({ "signature": signature.to_string(), "token_account": token_account.to_string(), "frozen": freeze }),
     );
 
-    println!("✅ Success! Mint Token Transaction: {}", explorer_link);
+    Ok(Some(signature))
+}
 
+fn validate_metadata_field(field: &str, value: &str, max_len: usize) -> Result<(), CliError> {
+    if value.len() > max_len {
+        return Err(format!(
+            "Token {} '{}' is {} bytes long, the limit is {} bytes",
+            field, value, value.len(), max_len
+        ).into());
+    }
     Ok(())
 }
 
-fn create_token_metadata() -> Result<(), Box<dyn std::error::Error>> {
-    let user = load_keypair_from_env();
+fn create_token_metadata(
+    config: &Config,
+    mint: Option<&str>,
+    name: &str,
+    symbol: &str,
+    uri: &str,
+    seller_fee_bps: u16,
+) -> Result<Option<Signature>, CliError> {
+    validate_metadata_field("name", name, MAX_NAME_LENGTH)?;
+    validate_metadata_field("symbol", symbol, MAX_SYMBOL_LENGTH)?;
+    validate_metadata_field("uri", uri, MAX_URI_LENGTH)?;
+    if seller_fee_bps > 10_000 {
+        return Err(CliError::InvalidArgument(format!("Seller fee must be at most 10000 basis points, got {}", seller_fee_bps)));
+    }
+
+    let user = load_signer(config)?;
 
-    let connection = create_connection();
+    let connection = create_connection(config);
     
-    let token_metadata_program_id = Pubkey::from_str("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s").unwrap();
+    let token_mint_account = resolve_mint(mint)?;
 
-    let token_mint_account = Pubkey::from_str("ExJmrjcJj3FuHNvswLkLmAxiEBGcdW5g9WnZqb8VjCiz").unwrap();
+    create_metadata(config, &connection, &*user, token_mint_account, name, symbol, uri, seller_fee_bps)
+}
 
-    let (metadata_pda, _bump) = Pubkey::find_program_address(
-        &[
-            b"metadata",
-            token_metadata_program_id.as_ref(),
-            token_mint_account.as_ref(),
-        ],
-        &token_metadata_program_id,
+#[allow(clippy::too_many_arguments)]
+fn create_metadata(
+    config: &Config,
+    connection: &impl SolanaClient,
+    user: &dyn Signer,
+    token_mint_account: Pubkey,
+    name: &str,
+    symbol: &str,
+    uri: &str,
+    seller_fee_bps: u16,
+) -> Result<Option<Signature>, CliError> {
+    let metadata_pda = find_metadata_pda(&config.metadata_program_id, &token_mint_account);
+
+    let create_metadata_account_instruction = create_metadata_instruction(
+        &config.metadata_program_id,
+        &token_mint_account,
+        &user.pubkey(),
+        name,
+        symbol,
+        uri,
+        seller_fee_bps,
+    );
+    
+    let tx_builder = TxBuilder::new(config, user).instruction(create_metadata_account_instruction);
+    let Some(signature) = tx_builder.send(connection)? else {
+        return Ok(None);
+    };
+
+    let explorer_link = config.link("address", token_mint_account);
+
+    config.output(
+        format!("✅ Look at the token mint again: {}", explorer_link),
+        json!({ "signature": signature.to_string(), "metadata": metadata_pda.to_string() }),
     );
 
+    Ok(Some(signature))
+}
+
+// The authority pays for, and becomes the update authority of, the mutable metadata.
+fn create_metadata_instruction(
+    metadata_program_id: &Pubkey,
+    token_mint_account: &Pubkey,
+    authority: &Pubkey,
+    name: &str,
+    symbol: &str,
+    uri: &str,
+    seller_fee_bps: u16,
+) -> Instruction {
     let metadata_data = DataV2 {
-        name: "Solana UA Bootcamp 2024-08-06".to_string(),
-        symbol: "UAB-2".to_string(),
-        uri: "https://arweave.net/1234".to_string(),
-        seller_fee_basis_points: 0,
+        name: name.to_string(),
+        symbol: symbol.to_string(),
+        uri: uri.to_string(),
+        seller_fee_basis_points: seller_fee_bps,
         creators: None,
         collection: None,
         uses: None,
     };
 
     let create_metadata_account_instruction = CreateMetadataAccountV3 {
-        metadata: metadata_pda,
-        mint: token_mint_account,
-        mint_authority: user.pubkey(),
-        payer: user.pubkey(),
-        update_authority: (user.pubkey(), true),
+        metadata: find_metadata_pda(metadata_program_id, token_mint_account),
+        mint: *token_mint_account,
+        mint_authority: *authority,
+        payer: *authority,
+        update_authority: (*authority, true),
         system_program: system_program::ID,
         rent: None,
     };
-    let create_metadata_account_instruction = create_metadata_account_instruction.instruction(
+    let instruction = create_metadata_account_instruction.instruction(
         CreateMetadataAccountV3InstructionArgs {
             data: metadata_data,
             is_mutable: true,
             collection_details: None,
         }
     );
-    
-    let mut transaction = Transaction::new_with_payer(
-        &[create_metadata_account_instruction],
-        Some(&user.pubkey()),
+    with_metadata_program(metadata_program_id, instruction)
+}
+
+// The generated Metaplex builders always target mpl_token_metadata::ID, and also pass it
+// in place of omitted optional accounts, so both are swapped for a --metadata-program-id.
+fn with_metadata_program(metadata_program_id: &Pubkey, mut instruction: Instruction) -> Instruction {
+    instruction.program_id = *metadata_program_id;
+    for account in &mut instruction.accounts {
+        if account.pubkey == mpl_token_metadata::ID {
+            account.pubkey = *metadata_program_id;
+        }
+    }
+    instruction
+}
+
+fn find_metadata_pda(metadata_program_id: &Pubkey, token_mint_account: &Pubkey) -> Pubkey {
+    let (metadata_pda, _bump) = Pubkey::find_program_address(
+        &[
+            b"metadata",
+            metadata_program_id.as_ref(),
+            token_mint_account.as_ref(),
+        ],
+        metadata_program_id,
+    );
+
+    metadata_pda
+}
+
+fn find_master_edition_pda(metadata_program_id: &Pubkey, token_mint_account: &Pubkey) -> Pubkey {
+    let (master_edition_pda, _bump) = Pubkey::find_program_address(
+        &[
+            b"metadata",
+            metadata_program_id.as_ref(),
+            token_mint_account.as_ref(),
+            b"edition",
+        ],
+        metadata_program_id,
+    );
+
+    master_edition_pda
+}
+
+fn update_token_metadata(
+    config: &Config,
+    mint: Option<&str>,
+    name: Option<&str>,
+    symbol: Option<&str>,
+    uri: Option<&str>,
+) -> Result<Option<Signature>, CliError> {
+    if name.is_none() && symbol.is_none() && uri.is_none() {
+        return Err(CliError::InvalidArgument("Nothing to update, pass at least one of --name, --symbol or --uri".to_string()));
+    }
+    validate_metadata_field("name", name.unwrap_or_default(), MAX_NAME_LENGTH)?;
+    validate_metadata_field("symbol", symbol.unwrap_or_default(), MAX_SYMBOL_LENGTH)?;
+    validate_metadata_field("uri", uri.unwrap_or_default(), MAX_URI_LENGTH)?;
+
+    let user = load_signer(config)?;
+    let token_mint_account = resolve_mint(mint)?;
+
+    let connection = create_connection(config);
+
+    let metadata_pda = find_metadata_pda(&config.metadata_program_id, &token_mint_account);
+    let metadata_account = connection.get_account(&metadata_pda)
+        .map_err(|e| format!("Failed to fetch the metadata of {}: {}", token_mint_account, e))?;
+    let metadata = Metadata::from_bytes(&metadata_account.data)
+        .map_err(|e| format!("Failed to decode the metadata of {}: {}", token_mint_account, e))?;
+
+    if metadata.update_authority != user.pubkey() {
+        return Err(format!(
+            "{} is not the update authority of {}, {} is",
+            user.pubkey(), token_mint_account, metadata.update_authority
+        ).into());
+    }
+    if !metadata.is_mutable {
+        return Err(format!("The metadata of {} is immutable", token_mint_account).into());
+    }
+
+    // On-chain strings are padded with NUL bytes up to their maximum length.
+    let metadata_data = DataV2 {
+        name: name.map_or_else(|| metadata.name.trim_end_matches('\0').to_string(), str::to_string),
+        symbol: symbol.map_or_else(|| metadata.symbol.trim_end_matches('\0').to_string(), str::to_string),
+        uri: uri.map_or_else(|| metadata.uri.trim_end_matches('\0').to_string(), str::to_string),
+        seller_fee_basis_points: metadata.seller_fee_basis_points,
+        creators: metadata.creators,
+        collection: metadata.collection,
+        uses: metadata.uses,
+    };
+
+    let update_metadata_account_instruction = UpdateMetadataAccountV2 {
+        metadata: metadata_pda,
+        update_authority: user.pubkey(),
+    };
+    let update_metadata_account_instruction = with_metadata_program(
+        &config.metadata_program_id,
+        update_metadata_account_instruction.instruction(
+            UpdateMetadataAccountV2InstructionArgs {
+                data: Some(metadata_data),
+                new_update_authority: None,
+                primary_sale_happened: None,
+                is_mutable: None,
+            }
+        ),
+    );
+
+    let tx_builder = TxBuilder::new(config, &*user).instruction(update_metadata_account_instruction);
+    let Some(signature) = tx_builder.send(&connection)? else {
+        return Ok(None);
+    };
+
+    let explorer_link = config.link("address", token_mint_account);
+
+    config.output(
+        format!("✅ Updated the token metadata: {}", explorer_link),
+        json!({ "signature": signature.to_string(), "metadata": metadata_pda.to_string() }),
+    );
+
+    Ok(Some(signature))
+}
+
+fn delete_token_metadata(config: &Config, mint: Option<&str>) -> Result<Option<Signature>, CliError> {
+    let user = load_signer(config)?;
+    let token_mint_account = resolve_mint(mint)?;
+
+    let connection = create_connection(config);
+
+    let metadata_pda = find_metadata_pda(&config.metadata_program_id, &token_mint_account);
+    let metadata_account = connection.get_account(&metadata_pda)
+        .map_err(|e| format!("Failed to fetch the metadata of {}: {}", token_mint_account, e))?;
+    let metadata = Metadata::from_bytes(&metadata_account.data)
+        .map_err(|e| format!("Failed to decode the metadata of {}: {}", token_mint_account, e))?;
+
+    if metadata.update_authority != user.pubkey() {
+        return Err(format!(
+            "{} is not the update authority of {}, {} is",
+            user.pubkey(), token_mint_account, metadata.update_authority
+        ).into());
+    }
+    if !metadata.is_mutable {
+        return Err(format!("The metadata of {} is immutable", token_mint_account).into());
+    }
+
+    confirm(config, &format!("Burn the metadata of {} and all of our tokens of it", token_mint_account))?;
+
+    // Metaplex only closes the metadata account as part of burning the asset,
+    // so the tokens held by the update authority are burned along with it.
+    let token_account = get_associated_token_address_with_program_id(
+        &user.pubkey(),
+        &token_mint_account,
+        &config.token_program_id,
     );
+    let balance = connection.get_token_account_balance(&token_account)
+        .map_err(|e| format!("Failed to read the balance of token account {}: {}", token_account, e))?;
+
+    let master_edition_pda = find_master_edition_pda(&config.metadata_program_id, &token_mint_account);
+    let master_edition = connection
+        .get_account_with_commitment(&master_edition_pda, connection.commitment())?
+        .value
+        .map(|_| master_edition_pda);
 
-    let recent_blockhash = connection.get_latest_blockhash()?;
-    transaction.sign(&[&user], recent_blockhash);
+    let burn_instruction = BurnV1Builder::new()
+        .authority(user.pubkey())
+        .metadata(metadata_pda)
+        .edition(master_edition)
+        .mint(token_mint_account)
+        .token(token_account)
+        .spl_token_program(config.token_program_id)
+        .amount(balance.amount.parse()?)
+        .instruction();
+    let burn_instruction = with_metadata_program(&config.metadata_program_id, burn_instruction);
 
-    let _signature = connection.send_and_confirm_transaction(&transaction)?;
+    let tx_builder = TxBuilder::new(config, &*user).instruction(burn_instruction);
+    let Some(signature) = tx_builder.send(&connection)? else {
+        return Ok(None);
+    };
+
+    let explorer_link = config.link("transaction", signature);
 
-    let explorer_link = format!(
-        "https://explorer.solana.com/address/{}?cluster=devnet",
-        token_mint_account
+    config.output(
+        format!(
+            "✅ Deleted the token metadata {}, reclaimed {} lamports: {}",
+            metadata_pda, metadata_account.lamports, explorer_link
+        ),
+        json!({
+            "signature": signature.to_string(),
+            "metadata": metadata_pda.to_string(),
+            "lamports": metadata_account.lamports,
+        }),
     );
 
-    println!("✅ Look at the token mint again: {}", explorer_link);
+    Ok(Some(signature))
+}
+
+fn show_token_metadata(config: &Config, mint: Option<&str>) -> Result<(), CliError> {
+    let token_mint_account = resolve_mint(mint)?;
+
+    let connection = create_connection(config);
+
+    let metadata_pda = find_metadata_pda(&config.metadata_program_id, &token_mint_account);
+    let Some(metadata_account) = connection
+        .get_account_with_commitment(&metadata_pda, connection.commitment())?
+        .value
+    else {
+        config.output(
+            format!("🔍 No metadata found for {}", token_mint_account),
+            json!({ "mint": token_mint_account.to_string(), "metadata": null }),
+        );
+        return Ok(());
+    };
+    let metadata = Metadata::from_bytes(&metadata_account.data)
+        .map_err(|e| format!("Failed to decode the metadata of {}: {}", token_mint_account, e))?;
+
+    let name = metadata.name.trim_end_matches('\0');
+    let symbol = metadata.symbol.trim_end_matches('\0');
+    let uri = metadata.uri.trim_end_matches('\0');
+
+    config.output(
+        format!(
+            "🏷️ Metadata of {} ({}):\n  Name: {}\n  Symbol: {}\n  URI: {}\n  Seller fee: {} bps\n  Update authority: {}\n  Mutable: {}",
+            token_mint_account,
+            metadata_pda,
+            name,
+            symbol,
+            uri,
+            metadata.seller_fee_basis_points,
+            metadata.update_authority,
+            metadata.is_mutable
+        ),
+        json!({
+            "mint": token_mint_account.to_string(),
+            "metadata": metadata_pda.to_string(),
+            "name": name,
+            "symbol": symbol,
+            "uri": uri,
+            "seller_fee_basis_points": metadata.seller_fee_basis_points,
+            "update_authority": metadata.update_authority.to_string(),
+            "is_mutable": metadata.is_mutable,
+        }),
+    );
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests;
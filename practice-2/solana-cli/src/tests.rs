@@ -0,0 +1,1035 @@
+use super::*;
+
+use solana_sdk::rent::Rent;
+
+use solana_program_test::{BanksClient, BanksClientError, ProgramTest};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use tokio::runtime::Runtime;
+
+// Serves canned accounts and balances and records what would have been sent.
+#[derive(Default)]
+struct MockClient {
+    accounts: HashMap<Pubkey, Account>,
+    balances: HashMap<Pubkey, u64>,
+    fee: u64,
+    sent: RefCell<Vec<Transaction>>,
+}
+
+impl SolanaClient for MockClient {
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.accounts
+            .get(pubkey)
+            .cloned()
+            .ok_or_else(|| ClientErrorKind::Custom(format!("AccountNotFound: pubkey={}", pubkey)).into())
+    }
+
+    fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        Ok(self.balances.get(pubkey).copied().unwrap_or_default())
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        Ok(Hash::new_from_array([7; 32]))
+    }
+
+    fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64> {
+        Ok(Rent::default().minimum_balance(data_len))
+    }
+
+    fn get_fee_for_message(&self, _message: &Message) -> ClientResult<u64> {
+        Ok(self.fee)
+    }
+
+    fn simulate_transaction(&self, _transaction: &Transaction) -> ClientResult<RpcSimulateTransactionResult> {
+        Ok(RpcSimulateTransactionResult {
+            err: None,
+            logs: Some(vec!["Program log: mocked".to_string()]),
+            accounts: None,
+            units_consumed: Some(150),
+            return_data: None,
+            inner_instructions: None,
+        })
+    }
+
+    fn send_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        self.sent.borrow_mut().push(transaction.clone());
+        Ok(transaction.signatures[0])
+    }
+
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        self.send_transaction(transaction)
+    }
+}
+
+// Drives an in-process bank through the same calls the commands make against RPC.
+struct BanksSolanaClient {
+    runtime: Runtime,
+    banks_client: BanksClient,
+}
+
+fn banks_error(e: BanksClientError) -> ClientError {
+    ClientErrorKind::Custom(e.to_string()).into()
+}
+
+impl SolanaClient for BanksSolanaClient {
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.runtime
+            .block_on(self.banks_client.clone().get_account(*pubkey))
+            .map_err(banks_error)?
+            .ok_or_else(|| ClientErrorKind::Custom(format!("AccountNotFound: pubkey={}", pubkey)).into())
+    }
+
+    fn get_balance(&self, pubkey: &Pubkey) -> ClientResult<u64> {
+        self.runtime
+            .block_on(self.banks_client.clone().get_balance(*pubkey))
+            .map_err(banks_error)
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.runtime
+            .block_on(self.banks_client.clone().get_latest_blockhash())
+            .map_err(banks_error)
+    }
+
+    fn get_minimum_balance_for_rent_exemption(&self, data_len: usize) -> ClientResult<u64> {
+        let rent = self.runtime
+            .block_on(self.banks_client.clone().get_rent())
+            .map_err(banks_error)?;
+        Ok(rent.minimum_balance(data_len))
+    }
+
+    fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64> {
+        self.runtime
+            .block_on(self.banks_client.clone().get_fee_for_message(message.clone()))
+            .map_err(banks_error)?
+            .ok_or_else(|| ClientErrorKind::Custom("Blockhash not found".to_string()).into())
+    }
+
+    fn simulate_transaction(&self, _transaction: &Transaction) -> ClientResult<RpcSimulateTransactionResult> {
+        Err(ClientErrorKind::Custom("Simulation is not supported by the test client".to_string()).into())
+    }
+
+    fn send_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        self.runtime
+            .block_on(self.banks_client.clone().send_transaction(transaction.clone()))
+            .map_err(banks_error)?;
+        Ok(transaction.signatures[0])
+    }
+
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        self.runtime
+            .block_on(self.banks_client.clone().process_transaction(transaction.clone()))
+            .map_err(banks_error)?;
+        Ok(transaction.signatures[0])
+    }
+}
+
+// Starts a bank with the SPL token, token-2022 and associated token account programs loaded.
+fn start_client() -> (BanksSolanaClient, Keypair) {
+    start_client_with(ProgramTest::default())
+}
+
+// Also loads the token metadata program from tests/fixtures/mpl_token_metadata.so.
+fn start_client_with_metadata() -> (BanksSolanaClient, Keypair) {
+    let mut program_test = ProgramTest::default();
+    program_test.add_program("mpl_token_metadata", mpl_token_metadata::ID, None);
+    start_client_with(program_test)
+}
+
+fn start_client_with(program_test: ProgramTest) -> (BanksSolanaClient, Keypair) {
+    let runtime = Runtime::new().unwrap();
+    let (banks_client, payer, _) = runtime.block_on(program_test.start());
+    (BanksSolanaClient { runtime, banks_client }, payer)
+}
+
+fn test_config(token_program_id: Pubkey) -> Config {
+    Config {
+        keypair_path: None,
+        keystore: None,
+        wallet: None,
+        wallet_secret: None,
+        ledger: None,
+        rpc_url: "http://localhost:8899".to_string(),
+        token_program_id,
+        json: true,
+        dry_run: false,
+        simulate: false,
+        dump_tx: false,
+        blockhash: None,
+        offline: None,
+        yes: false,
+        priority_fee: None,
+        compute_limit: None,
+        nonce_account: None,
+        nonce_authority: None,
+        output_dir: None,
+        websocket: false,
+        fee_payer: None,
+        fee_payer_keypair: None,
+        max_fee: LAMPORTS_PER_SOL / 100,
+        explorer_links: true,
+        metadata_program_id: mpl_token_metadata::ID,
+        quiet: false,
+        max_retries: 0,
+        commitment: CommitmentConfig::confirmed(),
+    }
+}
+
+fn token_account_amount(client: &BanksSolanaClient, token_account: &Pubkey) -> u64 {
+    let account = client.get_account(token_account).unwrap();
+    StateWithExtensions::<TokenAccount>::unpack(&account.data).unwrap().base.amount
+}
+
+#[test]
+fn create_mint_initializes_decimals_and_authorities() {
+    let (client, payer) = start_client();
+    let config = test_config(spl_token::id());
+    let mint_account = Keypair::new();
+    let freeze_authority = Pubkey::new_unique();
+
+    let mint = create_mint(&config, &client, &payer, &mint_account, &payer.pubkey(), Some(&freeze_authority), 6).unwrap();
+
+    assert_eq!(mint, mint_account.pubkey());
+    let mint_state = fetch_mint(&client, &mint).unwrap();
+    assert_eq!(mint_state.decimals, 6);
+    assert_eq!(mint_state.mint_authority, COption::Some(payer.pubkey()));
+    assert_eq!(mint_state.freeze_authority, COption::Some(freeze_authority));
+}
+
+#[test]
+fn create_mint_is_a_no_op_for_an_existing_matching_mint() {
+    let (client, payer) = start_client();
+    let config = test_config(spl_token::id());
+    let mint_account = Keypair::new();
+
+    let mint = create_mint(&config, &client, &payer, &mint_account, &payer.pubkey(), None, 6).unwrap();
+    let retried = create_mint(&config, &client, &payer, &mint_account, &payer.pubkey(), None, 6).unwrap();
+    assert_eq!(retried, mint);
+    assert!(create_mint(&config, &client, &payer, &mint_account, &payer.pubkey(), None, 2).is_err());
+    assert!(create_mint(&config, &client, &payer, &payer, &payer.pubkey(), None, 6).is_err());
+}
+
+#[test]
+#[ignore = "needs tests/fixtures/mpl_token_metadata.so, see tests/fixtures/README.md"]
+fn create_metadata_writes_the_metadata_account() {
+    let (client, payer) = start_client_with_metadata();
+    let config = test_config(spl_token::id());
+    let mint = create_mint(&config, &client, &payer, &Keypair::new(), &payer.pubkey(), None, 6).unwrap();
+
+    create_metadata(&config, &client, &payer, mint, "Test Token", "TEST", "https://example.com/test.json", 250).unwrap();
+
+    let account = client.get_account(&find_metadata_pda(&mpl_token_metadata::ID, &mint)).unwrap();
+    assert_eq!(account.owner, mpl_token_metadata::ID);
+    let metadata = Metadata::from_bytes(&account.data).unwrap();
+    assert_eq!(metadata.mint, mint);
+    assert_eq!(metadata.update_authority, payer.pubkey());
+    assert_eq!(metadata.name.trim_end_matches('\0'), "Test Token");
+    assert_eq!(metadata.symbol.trim_end_matches('\0'), "TEST");
+    assert_eq!(metadata.seller_fee_basis_points, 250);
+    assert!(metadata.is_mutable);
+
+    // The metadata account exists now, so creating it again fails.
+    assert!(create_metadata(&config, &client, &payer, mint, "Other", "OTH", "https://example.com/other.json", 0).is_err());
+}
+
+#[test]
+fn get_or_create_associated_token_account_creates_once() {
+    let (client, payer) = start_client();
+    let config = test_config(spl_token::id());
+    let mint = create_mint(&config, &client, &payer, &Keypair::new(), &payer.pubkey(), None, 2).unwrap();
+    let owner = Pubkey::new_unique();
+
+    let token_account = get_or_create_associated_token_account(&config, &client, &payer, &mint, &owner, &config.token_program_id).unwrap();
+    let again = get_or_create_associated_token_account(&config, &client, &payer, &mint, &owner, &config.token_program_id).unwrap();
+
+    assert_eq!(token_account, again);
+    assert_eq!(token_account, get_associated_token_address_with_program_id(&owner, &mint, &spl_token::id()));
+    assert_eq!(client.get_account(&token_account).unwrap().owner, spl_token::id());
+    assert_eq!(token_account_amount(&client, &token_account), 0);
+}
+
+#[test]
+fn mint_tokens_to_owner_credits_its_associated_token_account() {
+    let (client, payer) = start_client();
+    let config = test_config(spl_token::id());
+    let mint = create_mint(&config, &client, &payer, &Keypair::new(), &payer.pubkey(), None, 2).unwrap();
+    let owner = Pubkey::new_unique();
+    let token_account = get_or_create_associated_token_account(&config, &client, &payer, &mint, &owner, &config.token_program_id).unwrap();
+
+    let signature = mint_tokens_to(&config, &client, &payer, mint, owner, 1.5, &[]).unwrap();
+
+    assert!(signature.is_some());
+    assert_eq!(token_account_amount(&client, &token_account), 150);
+}
+
+#[test]
+fn mint_tokens_to_owner_without_a_token_account_creates_it() {
+    let (client, payer) = start_client();
+    let config = test_config(spl_token::id());
+    let mint = create_mint(&config, &client, &payer, &Keypair::new(), &payer.pubkey(), None, 2).unwrap();
+    let owner = Pubkey::new_unique();
+
+    mint_tokens_to(&config, &client, &payer, mint, owner, 3.0, &[]).unwrap();
+
+    let token_account = get_associated_token_address_with_program_id(&owner, &mint, &spl_token::id());
+    assert_eq!(token_account_amount(&client, &token_account), 300);
+}
+
+#[test]
+fn mint_tokens_to_works_with_token_2022() {
+    let (client, payer) = start_client();
+    let config = test_config(spl_token_2022::id());
+    let mint = create_mint(&config, &client, &payer, &Keypair::new(), &payer.pubkey(), None, 9).unwrap();
+    let token_account = get_or_create_associated_token_account(&config, &client, &payer, &mint, &payer.pubkey(), &config.token_program_id).unwrap();
+
+    // Passing the token account itself as --to mints straight into it.
+    mint_tokens_to(&config, &client, &payer, mint, token_account, 0.25, &[]).unwrap();
+
+    assert_eq!(client.get_account(&mint).unwrap().owner, spl_token_2022::id());
+    assert_eq!(token_account_amount(&client, &token_account), 250_000_000);
+}
+
+#[test]
+fn mint_tokens_to_rejects_a_signer_without_mint_authority() {
+    let (client, payer) = start_client();
+    let config = test_config(spl_token::id());
+    let mint = create_mint(&config, &client, &payer, &Keypair::new(), &Pubkey::new_unique(), None, 2).unwrap();
+
+    let error = mint_tokens_to(&config, &client, &payer, mint, payer.pubkey(), 1.0, &[]).unwrap_err();
+
+    assert!(error.to_string().contains("is not the mint authority"));
+}
+
+#[test]
+fn distribute_mints_to_every_owner_including_repeated_ones() {
+    let (client, payer) = start_client();
+    let config = test_config(spl_token::id());
+    let mint = create_mint(&config, &client, &payer, &Keypair::new(), &payer.pubkey(), None, 2).unwrap();
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let results = distribute(&config, &client, &payer, &mint, &[(first, 1.0), (second, 2.5), (first, 0.5)]).unwrap();
+
+    assert!(results.iter().all(Result::is_ok), "{:?}", results);
+    let token_account = |owner| get_associated_token_address_with_program_id(owner, &mint, &spl_token::id());
+    assert_eq!(token_account_amount(&client, &token_account(&first)), 150);
+    assert_eq!(token_account_amount(&client, &token_account(&second)), 250);
+}
+
+fn mint_account(mint_authority: &Pubkey, decimals: u8) -> Account {
+    let mut data = vec![0; Mint::LEN];
+    Mint {
+        mint_authority: COption::Some(*mint_authority),
+        supply: 0,
+        decimals,
+        is_initialized: true,
+        freeze_authority: COption::None,
+    }.pack_into_slice(&mut data);
+    Account { lamports: 1, data, owner: spl_token::id(), executable: false, rent_epoch: 0 }
+}
+
+fn transfer_transaction(payer: &Keypair, lamports: u64) -> Transaction {
+    Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), lamports)],
+        Some(&payer.pubkey()),
+        &[payer],
+        Hash::new_from_array([7; 32]),
+    )
+}
+
+#[test]
+fn fetch_mint_decimals_reads_the_mint_account() {
+    let mint = Pubkey::new_unique();
+    let client = MockClient {
+        accounts: HashMap::from([(mint, mint_account(&Pubkey::new_unique(), 6))]),
+        ..MockClient::default()
+    };
+
+    assert_eq!(fetch_mint_decimals(&client, &mint).unwrap(), 6);
+}
+
+#[test]
+fn fetch_mint_rejects_accounts_that_are_not_mints() {
+    let not_a_mint = Pubkey::new_unique();
+    let client = MockClient {
+        accounts: HashMap::from([(not_a_mint, Account { data: vec![1, 2, 3], ..Account::default() })]),
+        ..MockClient::default()
+    };
+
+    let error = fetch_mint(&client, &not_a_mint).unwrap_err();
+
+    assert!(error.to_string().contains("is not a token mint"));
+}
+
+#[test]
+fn rent_exemption_compares_lamports_with_the_minimum_for_the_data_size() {
+    let address = Pubkey::new_unique();
+    let client = MockClient {
+        accounts: HashMap::from([(address, Account { lamports: 42, data: vec![0; 165], ..Account::default() })]),
+        ..MockClient::default()
+    };
+
+    assert_eq!(rent_exemption(&client, &address).unwrap(), (42, Rent::default().minimum_balance(165)));
+    assert!(rent_exemption(&client, &Pubkey::new_unique()).is_err());
+}
+
+#[test]
+fn check_sufficient_funds_accounts_for_fee_and_rent() {
+    let payer = Keypair::new();
+    let rent_exempt_minimum = Rent::default().minimum_balance(0);
+    let mut client = MockClient { fee: 5_000, ..MockClient::default() };
+    let transaction = transfer_transaction(&payer, LAMPORTS_PER_SOL);
+    let config = test_config(spl_token::id());
+
+    client.balances.insert(payer.pubkey(), LAMPORTS_PER_SOL + 5_000 + rent_exempt_minimum);
+    assert!(check_sufficient_funds(&config, &client, &payer.pubkey(), LAMPORTS_PER_SOL, &transaction).is_ok());
+
+    client.balances.insert(payer.pubkey(), LAMPORTS_PER_SOL + 5_000);
+    let error = check_sufficient_funds(&config, &client, &payer.pubkey(), LAMPORTS_PER_SOL, &transaction).unwrap_err();
+    assert!(error.to_string().starts_with("insufficient funds"));
+    let needed = LAMPORTS_PER_SOL + 5_000 + rent_exempt_minimum;
+    assert!(matches!(error, CliError::InsufficientFunds { needed: n, available } if n == needed && available == LAMPORTS_PER_SOL + 5_000));
+}
+
+#[test]
+fn preflight_does_not_send_in_dry_run_or_simulate_mode() {
+    let payer = Keypair::new();
+    let client = MockClient::default();
+    let transaction = transfer_transaction(&payer, 1);
+
+    let dry_run = Config { dry_run: true, ..test_config(spl_token::id()) };
+    assert!(!preflight(&dry_run, &client, &transaction).unwrap());
+    let simulate = Config { simulate: true, ..test_config(spl_token::id()) };
+    assert!(!preflight(&simulate, &client, &transaction).unwrap());
+    assert!(preflight(&test_config(spl_token::id()), &client, &transaction).unwrap());
+
+    // One --offline file can't hold a second transaction.
+    let offline = Config { offline: Some("tx.b64".to_string()), ..test_config(spl_token::id()) };
+    assert!(check_offline_transaction_count(&offline, 1).is_ok());
+    assert_eq!(exit_code(&check_offline_transaction_count(&offline, 2).unwrap_err()), EXIT_BAD_ARGS);
+    assert!(check_offline_transaction_count(&test_config(spl_token::id()), 2).is_ok());
+}
+
+#[test]
+fn latest_blockhash_prefers_the_configured_blockhash() {
+    let client = MockClient::default();
+    let blockhash = Hash::new_unique();
+
+    let offline = Config { blockhash: Some(blockhash), ..test_config(spl_token::id()) };
+    assert_eq!(latest_blockhash(&offline, &client).unwrap(), blockhash);
+    assert_eq!(latest_blockhash(&test_config(spl_token::id()), &client).unwrap(), Hash::new_from_array([7; 32]));
+}
+
+#[test]
+fn mint_tokens_to_creates_a_missing_associated_token_account_in_the_same_transaction() {
+    let payer = Keypair::new();
+    let mint = Pubkey::new_unique();
+    let owner = Pubkey::new_unique();
+    let client = MockClient {
+        accounts: HashMap::from([(mint, mint_account(&payer.pubkey(), 3))]),
+        ..MockClient::default()
+    };
+
+    mint_tokens_to(&test_config(spl_token::id()), &client, &payer, mint, owner, 2.5, &[]).unwrap();
+
+    let sent = client.sent.borrow();
+    assert_eq!(sent.len(), 1);
+    let expected = mint_to(
+        &spl_token::id(),
+        &mint,
+        &get_associated_token_address_with_program_id(&owner, &mint, &spl_token::id()),
+        &payer.pubkey(),
+        &[],
+        2_500,
+    ).unwrap();
+    assert_eq!(sent[0].message.instructions.len(), 2);
+    let program_ids: Vec<_> = sent[0].message.instructions.iter()
+        .map(|instruction| sent[0].message.account_keys[instruction.program_id_index as usize])
+        .collect();
+    assert_eq!(program_ids, [spl_associated_token_account::id(), spl_token::id()]);
+    assert_eq!(sent[0].message.instructions[1].data, expected.data);
+}
+
+#[test]
+fn no_explorer_links_prints_bare_identifiers() {
+    let config = Config { rpc_url: DEVNET_RPC_URL.to_string(), ..test_config(spl_token::id()) };
+    assert_eq!(config.link("address", "abc"), "https://explorer.solana.com/address/abc?cluster=devnet");
+    let config = Config { explorer_links: false, ..config };
+    assert_eq!(config.link("address", "abc"), "abc");
+}
+
+#[test]
+fn explorer_link_follows_the_cluster() {
+    assert_eq!(
+        explorer_link("tx", "abc", DEVNET_RPC_URL),
+        "https://explorer.solana.com/tx/abc?cluster=devnet"
+    );
+    assert_eq!(
+        explorer_link("address", "abc", "https://api.mainnet-beta.solana.com/"),
+        "https://explorer.solana.com/address/abc"
+    );
+    assert_eq!(
+        explorer_link("address", "abc", "https://api.testnet.solana.com"),
+        "https://explorer.solana.com/address/abc?cluster=testnet"
+    );
+    assert_eq!(
+        explorer_link("address", "abc", "http://localhost:8899"),
+        "https://explorer.solana.com/address/abc?cluster=custom&customUrl=http%3A%2F%2Flocalhost%3A8899"
+    );
+}
+
+#[test]
+fn new_transaction_prepends_compute_budget_instructions() {
+    let payer = Pubkey::new_unique();
+    let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 1);
+
+    let plain = new_transaction(&test_config(spl_token::id()), std::slice::from_ref(&transfer), &payer);
+    assert_eq!(plain.message.instructions.len(), 1);
+
+    let config = Config { priority_fee: Some(1_000), compute_limit: Some(50_000), ..test_config(spl_token::id()) };
+    let prioritized = new_transaction(&config, &[transfer], &payer);
+    let program_ids: Vec<&Pubkey> = prioritized.message.instructions.iter()
+        .map(|instruction| instruction.program_id(&prioritized.message.account_keys))
+        .collect();
+    assert_eq!(program_ids, [&solana_sdk::compute_budget::id(), &solana_sdk::compute_budget::id(), &system_program::id()]);
+}
+
+#[test]
+fn nonce_transactions_use_the_stored_blockhash_and_advance_it_first() {
+    let payer = Keypair::new();
+    let nonce_account = Pubkey::new_unique();
+    let nonce_data = nonce::state::Data::new(
+        payer.pubkey(),
+        nonce::state::DurableNonce::from_blockhash(&Hash::new_unique()),
+        5_000,
+    );
+    let versions = nonce::state::Versions::new(nonce::State::Initialized(nonce_data.clone()));
+    let client = MockClient {
+        accounts: HashMap::from([(nonce_account, Account {
+            lamports: 1,
+            data: bincode::serialize(&versions).unwrap(),
+            owner: system_program::id(),
+            ..Account::default()
+        })]),
+        ..MockClient::default()
+    };
+    let config = Config { nonce_account: Some(nonce_account), ..test_config(spl_token::id()) };
+
+    let blockhash = latest_blockhash(&config, &client).unwrap();
+    assert_eq!(blockhash, nonce_data.blockhash());
+
+    let mut transaction = new_transaction(
+        &config,
+        &[system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), 1)],
+        &payer.pubkey(),
+    );
+    sign_transaction(&config, &mut transaction, &[&payer], blockhash).unwrap();
+    assert_eq!(transaction.message.instructions.len(), 2);
+    assert_eq!(
+        transaction.message.instructions[0].data,
+        system_instruction::advance_nonce_account(&nonce_account, &payer.pubkey()).data
+    );
+    assert!(transaction.verify().is_ok());
+}
+
+#[test]
+fn keystore_round_trips_only_with_the_right_passphrase() {
+    let keypair = Keypair::new();
+    let keystore = encrypt_keypair(&keypair, "correct horse").unwrap();
+    assert_eq!(keystore["pubkey"], keypair.pubkey().to_string());
+
+    let decrypted = decrypt_keypair(&keystore, "correct horse").unwrap();
+    assert_eq!(decrypted.to_bytes(), keypair.to_bytes());
+
+    let err = decrypt_keypair(&keystore, "battery staple").unwrap_err();
+    assert!(err.to_string().contains("wrong passphrase"), "{}", err);
+}
+
+#[test]
+fn output_dir_holds_relative_and_default_file_names() {
+    let output_dir = env::temp_dir().join(format!("solana-cli-test-{}", Pubkey::new_unique()));
+    let config = Config {
+        output_dir: Some(output_dir.to_string_lossy().into_owned()),
+        ..test_config(spl_token::id())
+    };
+    let pubkey = Pubkey::new_unique();
+
+    assert_eq!(config.output_file("tx.b64").unwrap(), output_dir.join("tx.b64").to_string_lossy());
+    assert_eq!(
+        config.default_output_file("mint", &pubkey).unwrap().unwrap(),
+        output_dir.join(format!("mint-{}.json", pubkey)).to_string_lossy()
+    );
+    assert!(output_dir.is_dir());
+    assert_eq!(test_config(spl_token::id()).default_output_file("mint", &pubkey).unwrap(), None);
+
+    fs::remove_dir_all(output_dir).unwrap();
+}
+
+#[test]
+fn read_address_file_skips_comments_and_reports_bad_lines() {
+    let path = env::temp_dir().join(format!("solana-cli-addresses-{}.txt", Pubkey::new_unique()));
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+    fs::write(&path, format!("# wallets\n{}\n\n  {}  \n", first, second)).unwrap();
+    assert_eq!(read_address_file(path.to_str().unwrap()).unwrap(), vec![first, second]);
+
+    fs::write(&path, format!("{}\nnot-a-pubkey\n", first)).unwrap();
+    let err = read_address_file(path.to_str().unwrap()).unwrap_err();
+    assert!(err.to_string().starts_with("Line 2 of"), "{}", err);
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn sweep_transaction_leaves_only_the_fee_or_the_rent_reserve() {
+    let sender = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    let client = MockClient {
+        balances: HashMap::from([(sender.pubkey(), LAMPORTS_PER_SOL)]),
+        fee: 5_000,
+        ..MockClient::default()
+    };
+    let config = test_config(spl_token::id());
+    let transfer_of = |lamports| system_instruction::transfer(&sender.pubkey(), &recipient, lamports).data;
+
+    let (transaction, amount) = sweep_transaction(&config, &client, &sender, &recipient, false).unwrap();
+    assert_eq!(amount, LAMPORTS_PER_SOL - 5_000);
+    assert_eq!(transaction.message.instructions[0].data, transfer_of(amount));
+    assert!(transaction.verify().is_ok());
+
+    let (_, amount) = sweep_transaction(&config, &client, &sender, &recipient, true).unwrap();
+    assert_eq!(amount, LAMPORTS_PER_SOL - 5_000 - Rent::default().minimum_balance(0));
+
+    let empty = MockClient { fee: 5_000, ..MockClient::default() };
+    assert!(sweep_transaction(&config, &empty, &sender, &recipient, false).is_err());
+
+    // With a separate --fee-payer the whole balance is swept.
+    let fee_payer = Keypair::new();
+    let config = Config { fee_payer: Some(fee_payer.pubkey()), fee_payer_keypair: Some(fee_payer), ..test_config(spl_token::id()) };
+    let (transaction, amount) = sweep_transaction(&config, &client, &sender, &recipient, false).unwrap();
+    assert_eq!(amount, LAMPORTS_PER_SOL);
+    assert_eq!(transaction.message.instructions[0].data, transfer_of(amount));
+}
+
+#[test]
+fn keypair_from_mnemonic_follows_the_derivation_path() {
+    let phrase = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    // Same address wallets like Phantom show for this well-known test phrase.
+    let keypair = keypair_from_mnemonic(phrase, "", DEFAULT_DERIVATION_PATH).unwrap();
+    assert_eq!(keypair.pubkey().to_string(), "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk");
+
+    assert_ne!(keypair_from_mnemonic(phrase, "", "m/44'/501'/1'/0'").unwrap().pubkey(), keypair.pubkey());
+    assert_ne!(keypair_from_mnemonic(phrase, "secret", DEFAULT_DERIVATION_PATH).unwrap().pubkey(), keypair.pubkey());
+    assert!(keypair_from_mnemonic("abandon about", "", DEFAULT_DERIVATION_PATH).is_err());
+}
+
+#[test]
+fn tx_builder_signs_with_every_signer_and_sends_once() {
+    let payer = Keypair::new();
+    let new_account = Keypair::new();
+    let client = MockClient::default();
+    let config = Config { compute_limit: Some(10_000), ..test_config(spl_token::id()) };
+
+    let signature = TxBuilder::new(&config, &payer)
+        .instruction(system_instruction::create_account(&payer.pubkey(), &new_account.pubkey(), 1, 0, &system_program::id()))
+        .signer(&new_account)
+        .send(&client)
+        .unwrap();
+
+    let sent = client.sent.borrow();
+    assert_eq!(sent.len(), 1);
+    assert_eq!(signature, Some(sent[0].signatures[0]));
+    assert_eq!(sent[0].signatures.len(), 2);
+    assert_eq!(sent[0].message.instructions.len(), 2);
+    assert!(sent[0].verify().is_ok());
+}
+
+#[test]
+fn exit_code_tells_error_categories_apart() {
+    let rpc_error = CliError::from(ClientError::from(ClientErrorKind::Custom("connection refused".to_string())));
+    assert_eq!(exit_code(&rpc_error), EXIT_RPC_FAILURE);
+    let rejected = CliError::from(ClientError::from(ClientErrorKind::TransactionError(TransactionError::InsufficientFundsForFee)));
+    assert_eq!(exit_code(&rejected), EXIT_INSUFFICIENT_FUNDS);
+
+    // The variant decides, not the wording.
+    assert_eq!(exit_code(&CliError::from("insufficient funds somewhere")), EXIT_FAILURE);
+    assert_eq!(exit_code(&CliError::from(std::io::Error::from(std::io::ErrorKind::NotFound))), EXIT_FAILURE);
+    assert_eq!(exit_code(&parse_pubkey("nope").unwrap_err()), EXIT_BAD_ARGS);
+    assert_eq!(exit_code(&CliError::from("1.5".parse::<u64>().unwrap_err())), EXIT_BAD_ARGS);
+    let config = test_config(spl_token::ID);
+    assert_eq!(exit_code(&create_token_mint(&config, 10, None, None, None, None).unwrap_err()), EXIT_BAD_ARGS);
+    assert_eq!(exit_code(&memo_instruction(&"x".repeat(MAX_MEMO_BYTES + 1)).unwrap_err()), EXIT_BAD_ARGS);
+}
+
+#[test]
+fn cli_errors_carry_their_category() {
+    assert!(matches!(parse_pubkey("nope"), Err(CliError::InvalidPubkey { value, .. }) if value == "nope"));
+    assert!(matches!(keypair_from_secret("[1, 2, 3]", "test"), Err(CliError::KeypairLoad(_))));
+    assert!(matches!(load_keypair_from_file("/nonexistent/id.json"), Err(CliError::KeypairLoad(_))));
+
+    let timeout = CliError::Timeout { signature: Signature::default(), seconds: 30 };
+    assert_eq!(exit_code(&timeout), EXIT_RPC_FAILURE);
+    assert!(timeout.to_string().contains("was not confirmed within 30 seconds"));
+    assert_eq!(exit_code(&CliError::InsufficientFunds { needed: 2, available: 1 }), EXIT_INSUFFICIENT_FUNDS);
+    assert_eq!(exit_code(&CliError::KeypairLoad("Failed to read keypair file".to_string())), EXIT_FAILURE);
+    assert_eq!(exit_code(&CliError::Interrupted("Interrupted after 10 attempts".to_string())), EXIT_INTERRUPTED);
+}
+
+#[test]
+fn verify_message_fails_on_a_signature_from_someone_else() {
+    let config = test_config(spl_token::id());
+    let (signer, other) = (Keypair::new(), Keypair::new());
+    let signature = signer.sign_message(b"hello").to_string();
+
+    assert!(verify_message(&config, &signer.pubkey().to_string(), "hello", &signature).is_ok());
+    let error = verify_message(&config, &other.pubkey().to_string(), "hello", &signature).unwrap_err();
+    assert!(matches!(error, CliError::SignatureMismatch { pubkey } if pubkey == other.pubkey()));
+    assert_eq!(exit_code(&error), EXIT_FAILURE);
+}
+
+#[test]
+fn websocket_url_follows_the_rpc_url() {
+    assert_eq!(websocket_url(DEVNET_RPC_URL), "wss://api.devnet.solana.com");
+    assert_eq!(websocket_url("http://localhost:8899"), "ws://localhost:8900");
+}
+
+#[test]
+fn vanity_search_finds_a_one_char_prefix_quickly() {
+    let search = VanitySearch::new("A", "", false, 2, Duration::from_secs(30)).unwrap();
+    let keypair = search.run().unwrap();
+    assert!(keypair.pubkey().to_string().starts_with('A'));
+    assert!(search.attempts() > 0);
+
+    let search = VanitySearch::new("", "z", true, 1, Duration::from_secs(30)).unwrap();
+    assert!(search.run().unwrap().pubkey().to_string().to_lowercase().ends_with('z'));
+}
+
+#[test]
+fn vanity_search_matches_any_prefix_or_a_character_class() {
+    let search = VanitySearch::new("", "", false, 1, Duration::from_secs(30)).unwrap()
+        .prefix_any(&["So1", "Sun"]).unwrap();
+    assert!((search.expected_attempts() - 58.0_f64.powi(3) / 2.0).abs() < 1e-6);
+    assert_eq!(search.describe(), "starting with any of 'So1', 'Sun'");
+    assert!(VanitySearch::new("", "", false, 1, Duration::from_secs(30)).unwrap().prefix_any(&["S0l"]).is_err());
+
+    let search = VanitySearch::new("", "", false, 2, Duration::from_secs(30)).unwrap()
+        .prefix_class("digits", 1).unwrap();
+    assert!((search.expected_attempts() - 58.0 / 9.0).abs() < 1e-9);
+    let keypair = search.run().unwrap();
+    assert!(keypair.pubkey().to_string().starts_with(|c: char| c.is_ascii_digit()));
+    assert!(VanitySearch::new("", "", false, 1, Duration::from_secs(30)).unwrap().prefix_class("emoji", 1).is_err());
+}
+
+#[test]
+fn vanity_search_validates_patterns_and_stops_at_the_deadline() {
+    assert!(VanitySearch::new("0", "", false, 1, Duration::from_secs(30)).is_err());
+    assert!(VanitySearch::new("", "l", false, 1, Duration::from_secs(30)).is_err());
+
+    let search = VanitySearch::new("zzzzzzzz", "", false, 1, Duration::ZERO).unwrap();
+    assert!(search.run().is_none());
+}
+
+#[test]
+fn fee_payer_transactions_collect_the_missing_signature() {
+    let (source, fee_payer) = (Keypair::new(), Keypair::new());
+    let config = Config { fee_payer: Some(fee_payer.pubkey()), ..test_config(spl_token::id()) };
+    let mut transaction = new_transaction(
+        &config,
+        &[system_instruction::transfer(&source.pubkey(), &Pubkey::new_unique(), 1)],
+        &source.pubkey(),
+    );
+    sign_transaction(&config, &mut transaction, &[&source], Hash::new_unique()).unwrap();
+    assert_eq!(missing_signers(&transaction), vec![fee_payer.pubkey()]);
+
+    assert!(add_transaction_signature(&mut transaction, &Keypair::new()).is_err());
+    add_transaction_signature(&mut transaction, &fee_payer).unwrap();
+    assert!(missing_signers(&transaction).is_empty());
+    assert!(transaction.verify().is_ok());
+    assert!(add_transaction_signature(&mut transaction, &fee_payer).is_err());
+}
+
+#[test]
+fn max_fee_rejects_expensive_transactions() {
+    let config = test_config(spl_token::id());
+    assert!(check_max_fee(&config, 5_000).is_ok());
+    assert!(check_max_fee(&config, LAMPORTS_PER_SOL / 100).is_ok());
+    let err = check_max_fee(&config, LAMPORTS_PER_SOL).unwrap_err().to_string();
+    assert!(err.contains("1 SOL exceeds the allowed 0.01 SOL"), "{}", err);
+
+    assert_eq!(parse_max_fee("0.01"), Ok(LAMPORTS_PER_SOL / 100));
+    assert_eq!(parse_max_fee("5000lamports"), Ok(5_000));
+    assert!(parse_max_fee("0").is_err());
+    assert!(parse_max_fee("-1").is_err());
+    assert!(parse_max_fee("NaN").is_err());
+}
+
+#[test]
+fn sol_amounts_accept_sol_and_lamport_units() {
+    assert_eq!(parse_sol_amount("0.01sol"), Ok(10_000_000));
+    assert_eq!(parse_sol_amount("0.01 SOL"), Ok(10_000_000));
+    assert_eq!(parse_sol_amount("100000lamports"), Ok(100_000));
+    assert_eq!(parse_sol_amount("1.5"), Ok(1_500_000_000));
+    assert_eq!(parse_sol_amount(".000000001"), Ok(1));
+    assert_eq!(parse_sol_amount("2"), Ok(2 * LAMPORTS_PER_SOL));
+
+    assert!(parse_sol_amount("0.0000000001").is_err());
+    assert!(parse_sol_amount("1.5lamports").is_err());
+    assert!(parse_sol_amount("-1").is_err());
+    assert!(parse_sol_amount("sol").is_err());
+    assert!(parse_sol_amount("99999999999999").is_err());
+}
+
+#[test]
+fn metadata_pdas_follow_the_metadata_program_id() {
+    let mint = Pubkey::new_unique();
+    assert_eq!(find_metadata_pda(&mpl_token_metadata::ID, &mint), mpl_token_metadata::accounts::Metadata::find_pda(&mint).0);
+    assert_eq!(
+        find_master_edition_pda(&mpl_token_metadata::ID, &mint),
+        mpl_token_metadata::accounts::MasterEdition::find_pda(&mint).0,
+    );
+
+    let forked_program_id = Pubkey::new_unique();
+    let authority = Pubkey::new_unique();
+    let instruction = create_metadata_instruction(&forked_program_id, &mint, &authority, "Name", "SYM", "https://example.com", 0);
+    assert_eq!(instruction.program_id, forked_program_id);
+    assert_eq!(instruction.accounts[0].pubkey, find_metadata_pda(&forked_program_id, &mint));
+    assert!(instruction.accounts.iter().all(|account| account.pubkey != mpl_token_metadata::ID));
+}
+
+#[test]
+fn signature_status_labels() {
+    use solana_sdk::transaction::TransactionError;
+    use solana_transaction_status::TransactionConfirmationStatus;
+
+    let status = |err, confirmation_status| TransactionStatus {
+        slot: 1,
+        confirmations: None,
+        status: Ok(()),
+        err,
+        confirmation_status,
+    };
+    assert_eq!(signature_status_label(None), "not found");
+    assert_eq!(signature_status_label(Some(&status(None, Some(TransactionConfirmationStatus::Confirmed)))), "confirmed");
+    assert_eq!(signature_status_label(Some(&status(None, Some(TransactionConfirmationStatus::Finalized)))), "finalized");
+    assert_eq!(
+        signature_status_label(Some(&status(Some(TransactionError::AccountNotFound), Some(TransactionConfirmationStatus::Finalized)))),
+        "failed",
+    );
+}
+
+#[test]
+fn fee_payer_keypair_pays_the_fee_and_signs() {
+    let (client, payer) = start_client();
+    let sender = Keypair::new();
+    let recipient = Pubkey::new_unique();
+    client.send_and_confirm_transaction(&Transaction::new_signed_with_payer(
+        &[system_instruction::transfer(&payer.pubkey(), &sender.pubkey(), LAMPORTS_PER_SOL)],
+        Some(&payer.pubkey()),
+        &[&payer],
+        client.get_latest_blockhash().unwrap(),
+    )).unwrap();
+    let fee_payer_balance = client.get_balance(&payer.pubkey()).unwrap();
+
+    let config = Config {
+        fee_payer: Some(payer.pubkey()),
+        fee_payer_keypair: Some(payer.insecure_clone()),
+        ..test_config(spl_token::id())
+    };
+    TxBuilder::new(&config, &sender)
+        .instruction(system_instruction::transfer(&sender.pubkey(), &recipient, LAMPORTS_PER_SOL / 2))
+        .send(&client)
+        .unwrap();
+
+    assert_eq!(client.get_balance(&sender.pubkey()).unwrap(), LAMPORTS_PER_SOL / 2);
+    assert!(client.get_balance(&payer.pubkey()).unwrap() < fee_payer_balance);
+}
+
+#[test]
+fn quiet_output_keeps_only_the_essential_field() {
+    assert_eq!(quiet_output(&json!({ "signature": "sig", "mint": "mint" })), Some("sig".to_string()));
+    assert_eq!(quiet_output(&json!({ "pubkey": "key", "lamports": 5, "sol": 0.5 })), Some("0.5".to_string()));
+    assert_eq!(quiet_output(&json!({ "pubkey": "key" })), Some("key".to_string()));
+    assert_eq!(quiet_output(&json!([{ "pubkey": "key" }])), None);
+    assert_eq!(quiet_output(&json!({ "pubkey": null })), None);
+}
+
+#[test]
+fn named_wallets_and_endpoints_resolve_from_the_config_file() {
+    let keypair = Keypair::new();
+    let tool_config = json!({
+        "wallets": {
+            "main": { "path": "/tmp/main.json" },
+            "cold": { "keystore": "/tmp/cold.json" },
+            "bot": { "secret": keypair.to_base58_string() },
+            "broken": { "path": "/tmp/a.json", "secret": "x" },
+        },
+        "endpoints": { "local": "http://localhost:8899" },
+    });
+
+    assert!(matches!(resolve_wallet(&tool_config, "main").unwrap(), Wallet::File(path) if path == "/tmp/main.json"));
+    assert!(matches!(resolve_wallet(&tool_config, "cold").unwrap(), Wallet::Keystore(path) if path == "/tmp/cold.json"));
+    assert!(resolve_wallet(&tool_config, "broken").is_err());
+    assert!(resolve_wallet(&tool_config, "missing").is_err());
+
+    let Wallet::Secret(secret) = resolve_wallet(&tool_config, "bot").unwrap() else { panic!("expected a secret") };
+    let config = Config { wallet: Some("bot".to_string()), wallet_secret: Some(secret), ..test_config(spl_token::id()) };
+    assert_eq!(load_signer(&config).unwrap().pubkey(), keypair.pubkey());
+
+    assert_eq!(resolve_endpoint(&tool_config, "local").unwrap(), "http://localhost:8899");
+    assert!(resolve_endpoint(&tool_config, "mainnet").is_err());
+}
+
+#[test]
+fn seeds_parse_strings_hex_and_pubkeys() {
+    let mint = Pubkey::new_unique();
+    assert_eq!(parse_seed("metadata").unwrap(), b"metadata");
+    assert_eq!(parse_seed("str:hex:not").unwrap(), b"hex:not");
+    assert_eq!(parse_seed("hex:0x00ff10").unwrap(), vec![0x00, 0xff, 0x10]);
+    assert_eq!(parse_seed(&format!("pubkey:{}", mint)).unwrap(), mint.to_bytes());
+    assert!(parse_seed("hex:abc").is_err());
+    assert!(parse_seed("hex:zz").is_err());
+    assert!(parse_seed(&"x".repeat(33)).is_err());
+
+    let seeds = [parse_seed("metadata").unwrap(), mpl_token_metadata::ID.to_bytes().to_vec(), mint.to_bytes().to_vec()];
+    let seed_slices: Vec<&[u8]> = seeds.iter().map(Vec::as_slice).collect();
+    let (pda, _) = Pubkey::find_program_address(&seed_slices, &mpl_token_metadata::ID);
+    assert_eq!(pda, find_metadata_pda(&mpl_token_metadata::ID, &mint));
+}
+
+#[test]
+fn fee_percentiles_use_the_nearest_rank() {
+    assert_eq!(fee_percentiles(vec![]), None);
+    assert_eq!(fee_percentiles(vec![7]), Some(vec![(25, 7), (50, 7), (75, 7), (90, 7), (100, 7)]));
+    let fees = (1..=10).rev().map(|fee| fee * 100).collect();
+    assert_eq!(fee_percentiles(fees), Some(vec![(25, 300), (50, 500), (75, 800), (90, 900), (100, 1000)]));
+}
+
+#[test]
+fn pack_transfers_splits_at_the_packet_size() {
+    let config = test_config(spl_token::id());
+    let sender = Pubkey::new_unique();
+    let transfers: Vec<_> = (0..40).map(|_| (Pubkey::new_unique(), LAMPORTS_PER_SOL)).collect();
+
+    let batches = pack_transfers(&config, &sender, transfers.clone()).unwrap();
+    assert!(batches.len() > 1);
+    assert_eq!(batches.concat(), transfers);
+    for batch in &batches {
+        let size = bincode::serialized_size(&transfers_transaction(&config, &sender, batch)).unwrap() as usize;
+        assert!(size <= PACKET_DATA_SIZE);
+    }
+}
+
+#[test]
+fn describe_transaction_lists_programs_accounts_and_data() {
+    let payer = Keypair::new();
+    let transaction = transfer_transaction(&payer, LAMPORTS_PER_SOL);
+
+    let description = describe_transaction(&transaction).unwrap();
+
+    assert_eq!(description["fee_payer"], json!(payer.pubkey().to_string()));
+    let decoded: Transaction = bincode::deserialize(&BASE64.decode(description["base64"].as_str().unwrap()).unwrap()).unwrap();
+    assert_eq!(decoded, transaction);
+    let instruction = &description["instructions"][0];
+    assert_eq!(instruction["program_id"], json!(system_program::id().to_string()));
+    assert_eq!(instruction["accounts"][0], json!({ "pubkey": payer.pubkey().to_string(), "signer": true, "writable": true }));
+    assert_eq!(instruction["accounts"][1]["signer"], json!(false));
+    let expected = system_instruction::transfer(&payer.pubkey(), &Pubkey::new_unique(), LAMPORTS_PER_SOL);
+    assert_eq!(instruction["data"], json!(bs58::encode(expected.data).into_string()));
+}
+
+#[test]
+fn format_duration_picks_the_largest_unit() {
+    assert_eq!(format_duration(0.25), "0.2 seconds");
+    assert_eq!(format_duration(90.0), "1.5 minutes");
+    assert_eq!(format_duration(7_200.0), "2.0 hours");
+    assert_eq!(format_duration(3.0 * 365.0 * 86_400.0), "3.0 years");
+}
+
+#[test]
+fn parse_batch_reads_csv_and_json_and_points_at_bad_lines() {
+    let (first, second) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let csv = format!("# owner,amount\n{},1.5\n\n{}, 2\n", first, second);
+    assert_eq!(parse_batch(&csv, "stdin", false, parse_batch_tokens).unwrap(), vec![(first, 1.5), (second, 2.0)]);
+    let json = format!("[{{\"pubkey\": \"{}\", \"amount\": 0.5}}]", first);
+    assert_eq!(parse_batch(&json, "stdin", true, parse_batch_tokens).unwrap(), vec![(first, 0.5)]);
+
+    let error = parse_batch(&format!("{},1\nnope,1\n", first), "stdin", false, parse_batch_tokens).unwrap_err();
+    assert!(error.to_string().contains("on line 2 of stdin"));
+    assert_eq!(exit_code(&error), EXIT_BAD_ARGS);
+    let error = parse_batch(&format!("{},1\n{}\n", first, second), "stdin", false, parse_batch_tokens).unwrap_err();
+    assert_eq!(error.to_string(), "Line 2 of stdin is not `pubkey,amount`");
+    assert_eq!(exit_code(&error), EXIT_BAD_ARGS);
+    assert_eq!(parse_batch("", "stdin", false, parse_batch_tokens).unwrap_err().to_string(), "stdin lists no recipients");
+}
+
+#[test]
+fn parse_batch_keeps_sol_amounts_exact() {
+    let pubkey = Pubkey::new_unique();
+
+    let csv = format!("{},0.3\n{},100lamports\n", pubkey, pubkey);
+    assert_eq!(parse_batch(&csv, "stdin", false, parse_batch_lamports).unwrap(), vec![(pubkey, 300_000_000), (pubkey, 100)]);
+    let json = format!("[{{\"pubkey\": \"{}\", \"amount\": 0.3}}]", pubkey);
+    assert_eq!(parse_batch(&json, "stdin", true, parse_batch_lamports).unwrap(), vec![(pubkey, 300_000_000)]);
+
+    let error = parse_batch(&format!("{},0\n", pubkey), "stdin", false, parse_batch_lamports).unwrap_err();
+    assert_eq!(error.to_string(), "Line 1 of stdin has an invalid amount: it must be greater than zero");
+    assert_eq!(exit_code(&error), EXIT_BAD_ARGS);
+}
+
+#[test]
+fn auto_rpc_tries_the_configured_mirrors_of_the_same_cluster() {
+    let tool_config = json!({ "mirrors": { "devnet": ["https://devnet.example.com"], "mainnet-beta": ["https://mainnet.example.com"] } });
+    assert_eq!(auto_rpc_candidates(&tool_config, DEVNET_RPC_URL).unwrap(), [DEVNET_RPC_URL, "https://devnet.example.com"]);
+
+    // A single candidate is refused rather than reported as the fastest.
+    let error = auto_rpc_candidates(&tool_config, "http://localhost:8899").unwrap_err();
+    assert!(error.to_string().starts_with("--auto-rpc has only http://localhost:8899 to measure"));
+    assert_eq!(exit_code(&error), EXIT_BAD_ARGS);
+    assert!(auto_rpc_candidates(&json!({}), DEVNET_RPC_URL).is_err());
+
+    let error = fastest_endpoint(&["http://127.0.0.1:1"]).unwrap_err();
+    assert!(error.to_string().starts_with("None of http://127.0.0.1:1 is healthy"));
+}
+
+#[test]
+fn memo_instruction_checks_the_length() {
+    let instruction = memo_instruction("invoice 42").unwrap();
+    assert_eq!(instruction.program_id.to_string(), "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+    assert_eq!(instruction.data, b"invoice 42");
+    assert!(instruction.accounts.is_empty());
+
+    assert!(memo_instruction(&"x".repeat(MAX_MEMO_BYTES)).is_ok());
+    let error = memo_instruction(&"x".repeat(MAX_MEMO_BYTES + 1)).unwrap_err();
+    assert!(error.to_string().contains("the memo program accepts at most"));
+}
+
+#[test]
+fn parse_account_size_accepts_bytes_and_presets() {
+    assert_eq!(parse_account_size("mint"), Ok(82));
+    assert_eq!(parse_account_size("token-account"), Ok(165));
+    assert_eq!(parse_account_size("multisig"), Ok(355));
+    assert_eq!(parse_account_size("0"), Ok(0));
+    assert_eq!(parse_account_size("1024"), Ok(1024));
+    assert!(parse_account_size("-1").is_err());
+    assert!(parse_account_size("nft").unwrap_err().contains("mint, token-account, multisig"));
+}
+
+#[test]
+fn parse_fee_payer_takes_a_pubkey_or_a_keypair_file() {
+    let pubkey = Pubkey::new_unique();
+    let (fee_payer, keypair) = parse_fee_payer(&pubkey.to_string()).unwrap();
+    assert_eq!(fee_payer, pubkey);
+    assert!(keypair.is_none());
+
+    let keypair = Keypair::new();
+    let path = env::temp_dir().join(format!("fee-payer-{}.json", keypair.pubkey()));
+    fs::write(&path, serde_json::to_string(&keypair.to_bytes().to_vec()).unwrap()).unwrap();
+    let (fee_payer, loaded) = parse_fee_payer(path.to_str().unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(fee_payer, keypair.pubkey());
+    assert_eq!(loaded.unwrap().pubkey(), keypair.pubkey());
+
+    // A mistyped path reports the file error rather than a bad pubkey.
+    let error = parse_fee_payer("/nonexistent/fee-payer.json").unwrap_err().to_string();
+    assert!(error.contains("Failed to read keypair file /nonexistent/fee-payer.json"));
+}